@@ -10,24 +10,158 @@
 //! // Create a new color scheme
 //! let scheme = ColorScheme::new();
 //!
-//! // Print with colors
-//! scheme.print_with_color("├──", "main.rs");
+//! // Write a colored entry to any `io::Write` sink
+//! let mut out = Vec::new();
+//! scheme.write_with_color(&mut out, "├──", "main.rs", false).unwrap();
+//!
+//! // Or format it as a string
+//! let line = scheme.format_with_separator("├──", " ", "main.rs", false);
 //!
 //! // Create a scheme without colors
 //! let no_color = ColorScheme::with_colors(false);
 //! ```
 
+use crate::config::ColorConfig;
 use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// ANSI code for dim (faint) text.
+const DIM: &str = "\x1b[2m";
+
+/// `--age-colors` gradient endpoints: the newest files are green, the oldest grey.
+const AGE_NEWEST: (u8, u8, u8) = (95, 215, 95);
+const AGE_OLDEST: (u8, u8, u8) = (108, 108, 108);
+
+/// Bold 256-color codes, ordered around the color wheel, that `--hash-colors`
+/// picks from for unconfigured extensions and `--color-by-top-dir` cycles through.
+const HASH_PALETTE: [&str; 12] = [
+    "\x1b[1;38;5;203m",
+    "\x1b[1;38;5;209m",
+    "\x1b[1;38;5;221m",
+    "\x1b[1;38;5;149m",
+    "\x1b[1;38;5;78m",
+    "\x1b[1;38;5;80m",
+    "\x1b[1;38;5;75m",
+    "\x1b[1;38;5;111m",
+    "\x1b[1;38;5;141m",
+    "\x1b[1;38;5;177m",
+    "\x1b[1;38;5;211m",
+    "\x1b[1;38;5;181m",
+];
+
+/// Built-in file categories: the `--legend` label, the extensions in the
+/// category and their default color.
+const CATEGORIES: [(&str, &[&str], &str); 5] = [
+    (
+        "scripts",
+        &[".fish", ".zsh", ".sh", ".nu", ".bash"],
+        "\x1b[1;32m",
+    ),
+    (
+        "configs",
+        &[".toml", ".json", ".yml", ".yaml", ".xml", ".ini", ".conf"],
+        "\x1b[1;33m",
+    ),
+    ("docs", &[".md", ".txt", ".rst"], "\x1b[1;36m"),
+    (
+        "source",
+        &[
+            ".rs", ".py", ".go", ".jl", ".js", ".ts", ".c", ".cpp", ".java",
+        ],
+        "\x1b[1;31m",
+    ),
+    ("plists", &[".plist", ".sublime"], "\x1b[1;35m"),
+];
+
+/// Shell startup files and other extensionless dotfile scripts, colored as scripts.
+const SCRIPT_NAMES: [&str; 12] = [
+    ".bashrc",
+    ".bash_profile",
+    ".bash_login",
+    ".bash_logout",
+    ".bash_aliases",
+    ".zshrc",
+    ".zshenv",
+    ".zprofile",
+    ".zlogin",
+    ".zlogout",
+    ".profile",
+    ".vimrc",
+];
+
+/// Bold blue, the built-in color for files outside every category.
+const DEFAULT_FILE: &str = "\x1b[1;34m";
+
+/// A preset palette for readers with a color-vision deficiency, selected with
+/// [`ColorScheme::set_theme`].
+///
+/// The presets avoid telling categories apart by red against green alone, and
+/// vary brightness and weight instead, underlining source files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    /// For reduced green sensitivity.
+    Deuteranopia,
+    /// For reduced red sensitivity.
+    Protanopia,
+}
+
+impl Theme {
+    /// Returns the codes for the `CATEGORIES`, in order, followed by the code
+    /// for other files.
+    const fn palette(self) -> [&'static str; 6] {
+        match self {
+            Self::Deuteranopia => [
+                "\x1b[1;38;5;27m",
+                "\x1b[1;38;5;214m",
+                "\x1b[38;5;117m",
+                "\x1b[1;4;38;5;220m",
+                "\x1b[1;38;5;175m",
+                "\x1b[38;5;250m",
+            ],
+            Self::Protanopia => [
+                "\x1b[1;38;5;33m",
+                "\x1b[1;38;5;226m",
+                "\x1b[38;5;153m",
+                "\x1b[1;4;38;5;214m",
+                "\x1b[1;38;5;141m",
+                "\x1b[38;5;250m",
+            ],
+        }
+    }
+}
 
 /// A structure representing a color scheme.
 ///
 /// This structure is used to represent a color scheme for the tree structure output.
+#[allow(clippy::struct_excessive_bools)] // each toggle has its own independent setter
 pub struct ColorScheme {
     enabled: bool,
     reset: String,
     folder: String,
+    folder_alt: String,
+    alternate_folders: bool,
+    dim_dot: bool,
+    hash_colors: bool,
+    highlight: String,
+    highlight_pattern: Option<HighlightPattern>,
+    line_background: String,
+    guides: String,
     default_file: String,
     extension_colors: HashMap<String, String>,
+    name_colors: HashMap<String, String>,
+    glob_colors: Vec<(glob::Pattern, String)>,
+    age_buckets: Option<AgeBuckets>,
+    newer_than: Option<u64>,
+    theme256: bool,
+}
+
+/// Reference time and bucket boundaries for coloring files by age.
+struct AgeBuckets {
+    now: u64,
+    /// Ascending upper bounds of each bucket's age, in seconds.
+    thresholds: Vec<u64>,
 }
 
 impl ColorScheme {
@@ -45,46 +179,55 @@ impl ColorScheme {
                 enabled: false,
                 reset: String::new(),
                 folder: String::new(),
+                folder_alt: String::new(),
+                alternate_folders: false,
+                dim_dot: false,
+                hash_colors: false,
+                highlight: String::new(),
+                highlight_pattern: None,
+                line_background: String::new(),
+                guides: String::new(),
                 default_file: String::new(),
                 extension_colors: HashMap::new(),
+                name_colors: HashMap::new(),
+                glob_colors: Vec::new(),
+                age_buckets: None,
+                newer_than: None,
+                theme256: false,
             };
         }
 
         let mut extension_colors = HashMap::new();
-
-        // Shell scripts
-        for ext in [".fish", ".zsh", ".sh", ".nu", ".bash"] {
-            extension_colors.insert(ext.to_string(), "\x1b[1;32m".to_string());
-        }
-
-        // Config files
-        for ext in [".toml", ".json", ".yml", ".yaml", ".xml", ".ini", ".conf"] {
-            extension_colors.insert(ext.to_string(), "\x1b[1;33m".to_string());
-        }
-
-        // Documentation
-        for ext in [".md", ".txt", ".rst"] {
-            extension_colors.insert(ext.to_string(), "\x1b[1;36m".to_string());
-        }
-
-        // Source code
-        for ext in [
-            ".rs", ".py", ".go", ".jl", ".js", ".ts", ".c", ".cpp", ".java",
-        ] {
-            extension_colors.insert(ext.to_string(), "\x1b[1;31m".to_string());
+        for (_, extensions, color) in CATEGORIES {
+            for ext in extensions {
+                extension_colors.insert((*ext).to_string(), color.to_string());
+            }
         }
 
-        // Plists and other
-        for ext in [".plist", ".sublime"] {
-            extension_colors.insert(ext.to_string(), "\x1b[1;35m".to_string());
+        let mut name_colors = HashMap::new();
+        for name in SCRIPT_NAMES {
+            name_colors.insert(name.to_string(), "\x1b[1;32m".to_string());
         }
 
         Self {
             enabled: true,
             reset: "\x1b[0m".to_string(),
             folder: "\x1b[1;37m".to_string(),
-            default_file: "\x1b[1;34m".to_string(),
+            folder_alt: "\x1b[1;36m".to_string(),
+            alternate_folders: false,
+            dim_dot: false,
+            hash_colors: false,
+            highlight: "\x1b[7m".to_string(),
+            highlight_pattern: None,
+            line_background: String::new(),
+            guides: String::new(),
+            default_file: DEFAULT_FILE.to_string(),
             extension_colors,
+            name_colors,
+            glob_colors: Vec::new(),
+            age_buckets: None,
+            newer_than: None,
+            theme256: false,
         }
     }
 
@@ -117,8 +260,235 @@ impl ColorScheme {
         base
     }
 
+    /// Create a color scheme from the `[colors]` section of the configuration.
+    ///
+    /// Applies everything [`ColorScheme::from_config`] does, plus whole-name rules
+    /// from `[colors.names]` and glob rules from `[[colors.glob-rules]]`. Glob
+    /// patterns are compiled once here; invalid patterns are reported and skipped.
+    #[must_use]
+    pub fn from_color_config(config: &ColorConfig) -> Self {
+        let mut scheme = Self::with_colors(config.enabled);
+        if scheme.enabled {
+            scheme.apply_color_config(config);
+        }
+        scheme
+    }
+
+    /// Create a color scheme from the configuration alone, without the built-in
+    /// extension and file name rules.
+    ///
+    /// Only the folder and default file colors keep their defaults; every other
+    /// rule comes from the `[colors]` section.
+    #[must_use]
+    pub fn from_color_config_only(config: &ColorConfig) -> Self {
+        let mut scheme = Self::with_colors(config.enabled);
+        if scheme.enabled {
+            scheme.extension_colors.clear();
+            scheme.name_colors.clear();
+            scheme.apply_color_config(config);
+        }
+        scheme
+    }
+
+    /// Layers the rules from the `[colors]` section on top of this scheme.
+    fn apply_color_config(&mut self, config: &ColorConfig) {
+        if let Some(color) = &config.folder {
+            self.folder = Self::parse_color(color);
+        }
+
+        if let Some(color) = &config.folder_alt {
+            self.folder_alt = Self::parse_color(color);
+        }
+
+        if let Some(color) = &config.default_file {
+            self.default_file = Self::parse_color(color);
+        }
+
+        if let Some(color) = &config.highlight {
+            self.highlight = Self::parse_color(color);
+        }
+
+        if let Some(color) = &config.line_background {
+            match Self::parse_background(color) {
+                Some(code) => self.line_background = code,
+                None => eprintln!("Warning: ignoring invalid line background {color:?}"),
+            }
+        }
+
+        for (ext, color) in &config.extensions {
+            self.extension_colors
+                .insert(ext.clone(), Self::parse_color(color));
+        }
+
+        for (name, color) in &config.names {
+            self.name_colors
+                .insert(name.clone(), Self::parse_color(color));
+        }
+
+        for rule in &config.glob_rules {
+            match glob::Pattern::new(&rule.pattern) {
+                Ok(pattern) => self
+                    .glob_colors
+                    .push((pattern, Self::parse_color(&rule.color))),
+                Err(e) => eprintln!(
+                    "Warning: ignoring invalid color glob {:?}: {e}",
+                    rule.pattern
+                ),
+            }
+        }
+    }
+
+    /// Alternates directory colors by depth: directories at odd depths use the
+    /// `folder-alt` color, those at even depths the regular folder color.
+    pub const fn set_alternate_folders(&mut self, enabled: bool) {
+        self.alternate_folders = enabled;
+    }
+
+    /// Renders the leading `.` of dotfile names dimmed, so `.gitignore` reads as
+    /// a de-emphasized dot followed by `gitignore` in its usual color.
+    pub const fn set_dim_dot(&mut self, enabled: bool) {
+        self.dim_dot = enabled;
+    }
+
+    /// Colors files whose extension has no configured or built-in color with a
+    /// palette entry chosen by hashing the extension, so the same extension
+    /// always gets the same color.
+    pub const fn set_hash_colors(&mut self, enabled: bool) {
+        self.hash_colors = enabled;
+    }
+
+    /// Colors the connector lines (`├──`, `│`, ...) with `color`, independently
+    /// of the names. Ignored when colors are disabled.
+    pub fn set_guides_color(&mut self, color: Option<&str>) {
+        if self.enabled {
+            self.guides = color
+                .map(|color| self.quantize(Self::parse_color(color)))
+                .unwrap_or_default();
+        }
+    }
+
+    /// Recolors the built-in file categories and other files with the palette
+    /// of `theme`. Colors set by the config are kept. Ignored when colors are
+    /// disabled.
+    pub fn set_theme(&mut self, theme: Theme) {
+        if !self.enabled {
+            return;
+        }
+
+        let palette = theme.palette();
+        let recolor = |code: &mut String, builtin: &str, themed: &str| {
+            if code == builtin {
+                *code = themed.to_string();
+            }
+        };
+        for ((_, extensions, builtin), themed) in CATEGORIES.iter().zip(palette) {
+            for ext in *extensions {
+                if let Some(code) = self.extension_colors.get_mut(*ext) {
+                    recolor(code, builtin, themed);
+                }
+            }
+        }
+        // Script names share the color of the script extensions
+        for name in SCRIPT_NAMES {
+            if let Some(code) = self.name_colors.get_mut(name) {
+                recolor(code, CATEGORIES[0].2, palette[0]);
+            }
+        }
+        recolor(&mut self.default_file, DEFAULT_FILE, palette[5]);
+    }
+
+    /// Converts every 24-bit color, configured or built in, to the nearest
+    /// xterm 256-color palette entry, for terminals without truecolor support.
+    pub fn set_theme256(&mut self, enabled: bool) {
+        self.theme256 = enabled;
+        if !enabled {
+            return;
+        }
+
+        for code in [
+            &mut self.folder,
+            &mut self.folder_alt,
+            &mut self.default_file,
+            &mut self.highlight,
+            &mut self.line_background,
+            &mut self.guides,
+        ] {
+            *code = to_ansi256(code);
+        }
+        for code in self
+            .extension_colors
+            .values_mut()
+            .chain(self.name_colors.values_mut())
+            .chain(self.glob_colors.iter_mut().map(|(_, code)| code))
+        {
+            *code = to_ansi256(code);
+        }
+    }
+
+    /// Returns `code` converted to the 256-color palette if `--theme256` is on.
+    fn quantize(&self, code: String) -> String {
+        if self.theme256 {
+            to_ansi256(&code)
+        } else {
+            code
+        }
+    }
+
+    /// Enables coloring files by age: a file whose modification time is at
+    /// most `thresholds[i]` seconds before `now` falls in bucket `i`, and older
+    /// files in the last bucket. Buckets run on a gradient from green (newest)
+    /// to grey (oldest).
+    pub fn set_age_buckets(&mut self, now: u64, thresholds: &[u64]) {
+        let mut thresholds = thresholds.to_vec();
+        thresholds.sort_unstable();
+        self.age_buckets = Some(AgeBuckets { now, thresholds });
+    }
+
+    /// Returns the age color for a file modified at `mtime` (seconds since the
+    /// Unix epoch), or `None` if colors or age buckets are disabled.
+    #[must_use]
+    pub fn age_color(&self, mtime: u64) -> Option<String> {
+        let buckets = self.age_buckets.as_ref().filter(|_| self.enabled)?;
+        let age = buckets.now.saturating_sub(mtime);
+        let bucket = age_bucket(age, &buckets.thresholds);
+        Some(self.quantize(age_gradient(bucket, buckets.thresholds.len())))
+    }
+
+    /// Highlights files modified after `reference` (seconds since the Unix
+    /// epoch), for `--newer-than`. `None` turns this off.
+    pub const fn set_newer_than(&mut self, reference: Option<u64>) {
+        self.newer_than = reference;
+    }
+
+    /// Returns the highlighted color for the file `name` if it was modified at
+    /// `mtime`, after the [`set_newer_than`](Self::set_newer_than) reference,
+    /// or `None` if it is older or colors are disabled.
+    #[must_use]
+    pub fn newer_color(&self, name: &str, mtime: u64) -> Option<String> {
+        self.newer_than
+            .filter(|&reference| self.enabled && mtime > reference)
+            .map(|_| format!("{}{}", self.get_color_code_for_file(name), self.highlight))
+    }
+
+    /// Highlights the part of each name matched by the glob `pattern` (as used by
+    /// `--find`). Only the pattern's last path component is matched against names,
+    /// and only characters matched literally are highlighted.
+    /// Invalid patterns highlight nothing.
+    pub fn set_highlight_pattern(&mut self, pattern: Option<&str>) {
+        self.highlight_pattern = pattern.and_then(|pattern| {
+            HighlightPattern::new(pattern.rsplit('/').next().unwrap_or(pattern))
+        });
+    }
+
     /// Parse color names to ANSI codes.
+    ///
+    /// `#rgb` and `#rrggbb` hex colors become bold 24-bit codes; anything else
+    /// that isn't a color name is used as a custom ANSI code.
     fn parse_color(color: &str) -> String {
+        if let Some((r, g, b)) = color.strip_prefix('#').and_then(parse_hex) {
+            return format!("\x1b[1;38;2;{r};{g};{b}m");
+        }
+
         match color.to_lowercase().as_str() {
             "black" => "\x1b[1;30m".to_string(),
             "red" => "\x1b[1;31m".to_string(),
@@ -132,41 +502,531 @@ impl ColorScheme {
         }
     }
 
+    /// Parse a background color to an ANSI code.
+    ///
+    /// Accepts `#rgb` and `#rrggbb` hex colors (as 24-bit codes), the basic color
+    /// names, or a custom ANSI code. Returns `None` for malformed hex colors.
+    fn parse_background(color: &str) -> Option<String> {
+        if let Some(hex) = color.strip_prefix('#') {
+            let (r, g, b) = parse_hex(hex)?;
+            return Some(format!("\x1b[48;2;{r};{g};{b}m"));
+        }
+
+        let code = match color.to_lowercase().as_str() {
+            "black" => "\x1b[40m",
+            "red" => "\x1b[41m",
+            "green" => "\x1b[42m",
+            "yellow" => "\x1b[43m",
+            "blue" => "\x1b[44m",
+            "magenta" => "\x1b[45m",
+            "cyan" => "\x1b[46m",
+            "white" => "\x1b[47m",
+            _ => color, // Allow custom ANSI codes
+        };
+        Some(code.to_string())
+    }
+
+    /// Wraps a connector prefix in the guides color, if one is set.
+    fn paint_guides(&self, prefix: &str) -> String {
+        if self.guides.is_empty() || prefix.is_empty() {
+            prefix.to_string()
+        } else {
+            format!("{}{prefix}{}", self.guides, self.reset)
+        }
+    }
+
+    /// Applies the line background to a fully formatted line.
+    ///
+    /// Every reset inside the line would also clear the background, so the
+    /// background is re-applied after each one and the line ends with a reset.
+    fn with_line_background(&self, line: String) -> String {
+        if self.line_background.is_empty() {
+            return line;
+        }
+
+        let bg = &self.line_background;
+        let reset = &self.reset;
+        let line = line.strip_suffix(reset.as_str()).unwrap_or(&line);
+        let line = line.replace(reset.as_str(), &format!("{reset}{bg}"));
+        format!("{bg}{line}{reset}")
+    }
+
     /// Returns the color code for a given file based on its extension.
     fn get_color_code_for_file(&self, name: &str) -> &str {
         if !self.enabled {
             return "";
         }
 
+        if let Some(color) = self.name_colors.get(name) {
+            return color;
+        }
+
+        if let Some((_, color)) = self
+            .glob_colors
+            .iter()
+            .find(|(pattern, _)| pattern.matches(name))
+        {
+            return color;
+        }
+
         for (ext, color) in &self.extension_colors {
             if name.ends_with(ext) {
                 return color;
             }
         }
 
+        if self.hash_colors
+            && let Some(ext) = extension(name)
+        {
+            return hash_color(ext);
+        }
+
         &self.default_file
     }
 
-    /// Prints a string with a color prefix based on the file type.
+    /// Returns the color code the rules pick for the file `name`, or `None`
+    /// when colors are disabled.
+    #[must_use]
+    pub fn file_color(&self, name: &str) -> Option<&str> {
+        self.enabled.then(|| self.get_color_code_for_file(name))
+    }
+
+    /// Returns the code that ends a colored run, or `""` when colors are disabled.
+    #[must_use]
+    pub fn reset_code(&self) -> &str {
+        &self.reset
+    }
+
+    /// Formats the root label of the tree using the folder color.
+    #[must_use]
+    pub fn format_root(&self, name: &str) -> String {
+        if !self.enabled {
+            return name.to_string();
+        }
+
+        self.with_line_background(format!("{}{name}{}", self.folder, self.reset))
+    }
+
+    /// Writes a colored entry and a newline to `writer`, with a space between
+    /// the prefix and the name.
+    ///
+    /// Directories (`is_dir`, e.g. `!node.is_leaf`) get the folder color whatever
+    /// their name, so a directory such as `my.config` is still a folder and a
+    /// file such as `Makefile` is still a file. Files are colored by name and
+    /// extension.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `writer`.
+    pub fn write_with_color<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        prefix: &str,
+        name: &str,
+        is_dir: bool,
+    ) -> io::Result<()> {
+        writeln!(
+            writer,
+            "{}",
+            self.format_with_separator(prefix, " ", name, is_dir)
+        )
+    }
+
+    /// Formats a colored entry, joining the prefix and name with `separator`.
     ///
-    /// Files without a dot in their name are treated as folders and colored accordingly.
-    /// Files with extensions are colored based on their extension.
-    pub fn print_with_color(&self, prefix: &str, name: &str) {
+    /// Coloring follows the same rules as [`ColorScheme::write_with_color`];
+    /// there is no trailing newline.
+    #[must_use]
+    pub fn format_with_separator(
+        &self,
+        prefix: &str,
+        separator: &str,
+        name: &str,
+        is_dir: bool,
+    ) -> String {
+        self.format_at_depth(prefix, separator, name, is_dir, 0, None)
+    }
+
+    /// Formats a colored entry found at `depth` (top-level entries are at depth 1).
+    ///
+    /// Coloring follows [`ColorScheme::write_with_color`]. With alternating
+    /// folders enabled, directories at odd depths use the alternate
+    /// folder color. A `color` given by the caller, such as an
+    /// [`age_color`](Self::age_color), replaces the color the rules would pick.
+    #[must_use]
+    pub fn format_at_depth(
+        &self,
+        prefix: &str,
+        separator: &str,
+        name: &str,
+        is_dir: bool,
+        depth: usize,
+        color: Option<&str>,
+    ) -> String {
         if !self.enabled {
-            println!("{prefix} {name}");
-            return;
+            return format!("{prefix}{separator}{name}");
         }
 
-        let color_code = if name.contains('.') {
-            self.get_color_code_for_file(name)
-        } else {
-            &self.folder
+        let color_code = color.unwrap_or_else(|| {
+            if !is_dir {
+                self.get_color_code_for_file(name)
+            } else if self.alternate_folders && depth % 2 == 1 {
+                &self.folder_alt
+            } else {
+                &self.folder
+            }
+        });
+
+        // --find matches leaves, so only their names are highlighted
+        let highlighted = self
+            .highlight_pattern
+            .as_ref()
+            .filter(|_| !is_dir)
+            .map(|pattern| pattern.spans(name))
+            .unwrap_or_default();
+        let dim_dot = self.dim_dot && name.len() > 1 && name.starts_with('.');
+
+        // Split the name into runs that share a style, each closed by a reset
+        let style_at = |index: usize| {
+            if highlighted.iter().any(|span| span.contains(&index)) {
+                format!("{color_code}{}", self.highlight)
+            } else if dim_dot && index == 0 {
+                DIM.to_string()
+            } else {
+                color_code.to_string()
+            }
         };
 
-        println!("{prefix} {color_code}{name}{}", self.reset);
+        let mut line = format!("{}{separator}", self.paint_guides(prefix));
+        let mut run_start = 0;
+        let mut run_style = style_at(0);
+        for (index, _) in name.char_indices().skip(1) {
+            let style = style_at(index);
+            if style != run_style {
+                let _ = write!(line, "{run_style}{}{}", &name[run_start..index], self.reset);
+                run_start = index;
+                run_style = style;
+            }
+        }
+        let _ = write!(line, "{run_style}{}{}", &name[run_start..], self.reset);
+        self.with_line_background(line)
+    }
+
+    /// Returns the color for everything below the `index`th top-level directory
+    /// with `--color-by-top-dir`, or `None` if colors are disabled.
+    ///
+    /// Consecutive indexes step five places around the palette so neighboring
+    /// directories get clearly different hues; the colors repeat after the
+    /// twelfth directory.
+    #[must_use]
+    pub fn top_dir_color(&self, index: usize) -> Option<String> {
+        self.enabled
+            .then(|| HASH_PALETTE[index.wrapping_mul(5) % HASH_PALETTE.len()].to_string())
+    }
+
+    /// Formats a `--legend` line with a colored swatch for each built-in file
+    /// category that appears among `paths`, e.g. `■ scripts  ■ docs`.
+    ///
+    /// Each swatch uses the color of the first file seen in its category, so
+    /// configured colors are reflected. Returns `None` when colors are disabled
+    /// or no file is given.
+    #[must_use]
+    pub fn format_legend<S: AsRef<str>>(&self, paths: &[S]) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+
+        let names: Vec<&str> = paths
+            .iter()
+            .map(|path| path.as_ref().rsplit('/').next().unwrap_or_default())
+            .collect();
+        let labels = CATEGORIES
+            .iter()
+            .map(|(label, _, _)| *label)
+            .chain(["other"]);
+        let entries: Vec<String> = labels
+            .filter_map(|label| {
+                let name = names.iter().find(|name| file_category(name) == label)?;
+                let color = self.get_color_code_for_file(name);
+                Some(format!("{color}■{} {label}", self.reset))
+            })
+            .collect();
+
+        (!entries.is_empty()).then(|| entries.join("  "))
+    }
+
+    /// Formats an entry with its name dimmed instead of colored, e.g. for a
+    /// directory that contains no files.
+    #[must_use]
+    pub fn format_dimmed(&self, prefix: &str, separator: &str, name: &str) -> String {
+        if !self.enabled {
+            return format!("{prefix}{separator}{name}");
+        }
+
+        let prefix = self.paint_guides(prefix);
+        self.with_line_background(format!("{prefix}{separator}{DIM}{name}{}", self.reset))
     }
 }
 
+/// Returns the built-in category of the file `name` (`scripts`, `configs`,
+/// `docs`, `source` or `plists`), or `other` if it has none.
+#[must_use]
+pub fn file_category(name: &str) -> &'static str {
+    if SCRIPT_NAMES.contains(&name) {
+        return "scripts";
+    }
+    CATEGORIES
+        .iter()
+        .find(|(_, extensions, _)| extensions.iter().any(|ext| name.ends_with(ext)))
+        .map_or("other", |(label, _, _)| label)
+}
+
+/// Returns the index of the first bucket whose threshold `age` doesn't exceed,
+/// or `thresholds.len()` if it is older than all of them.
+fn age_bucket(age: u64, thresholds: &[u64]) -> usize {
+    thresholds.partition_point(|&threshold| threshold < age)
+}
+
+/// Returns the bold 24-bit color `bucket` steps of `last` along the gradient
+/// from `AGE_NEWEST` to `AGE_OLDEST`.
+fn age_gradient(bucket: usize, last: usize) -> String {
+    let mix = |newest: u8, oldest: u8| {
+        let (newest, oldest) = (usize::from(newest), usize::from(oldest));
+        let mixed = (newest * (last - bucket) + oldest * bucket)
+            .checked_div(last)
+            .unwrap_or(newest);
+        u8::try_from(mixed).unwrap_or(u8::MAX)
+    };
+    let r = mix(AGE_NEWEST.0, AGE_OLDEST.0);
+    let g = mix(AGE_NEWEST.1, AGE_OLDEST.1);
+    let b = mix(AGE_NEWEST.2, AGE_OLDEST.2);
+    format!("\x1b[1;38;2;{r};{g};{b}m")
+}
+
+/// Returns the extension of `name`, including its dot.
+///
+/// A leading dot starts a dotfile name rather than an extension, so `.zshrc`
+/// has none.
+fn extension(name: &str) -> Option<&str> {
+    name.rfind('.')
+        .filter(|&dot| dot > 0 && dot + 1 < name.len())
+        .map(|dot| &name[dot..])
+}
+
+/// Picks a `HASH_PALETTE` color for `ext` using the FNV-1a hash, which unlike
+/// the standard library's hasher is stable across runs and releases.
+fn hash_color(ext: &str) -> &'static str {
+    let hash = ext.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    // The remainder is below the palette length, so it always fits in usize
+    #[allow(clippy::cast_possible_truncation)]
+    HASH_PALETTE[(hash % HASH_PALETTE.len() as u64) as usize]
+}
+
+/// Rewrites the 24-bit `38;2;r;g;b` and `48;2;r;g;b` parameters of an ANSI
+/// code as the nearest `38;5;n` and `48;5;n` 256-color ones, keeping the
+/// other parameters. Codes that aren't SGR sequences are returned unchanged.
+fn to_ansi256(code: &str) -> String {
+    let Some(params) = code
+        .strip_prefix("\x1b[")
+        .and_then(|code| code.strip_suffix('m'))
+    else {
+        return code.to_string();
+    };
+
+    let params: Vec<&str> = params.split(';').collect();
+    let mut converted = Vec::with_capacity(params.len());
+    let mut i = 0;
+    while i < params.len() {
+        let channels = params
+            .get(i + 2..i + 5)
+            .filter(|_| matches!(params[i], "38" | "48") && params[i + 1] == "2")
+            .and_then(|rgb| {
+                Some((
+                    rgb[0].parse().ok()?,
+                    rgb[1].parse().ok()?,
+                    rgb[2].parse().ok()?,
+                ))
+            });
+        if let Some((r, g, b)) = channels {
+            converted.push(format!("{};5;{}", params[i], rgb_to_ansi256(r, g, b)));
+            i += 5;
+        } else {
+            converted.push(params[i].to_string());
+            i += 1;
+        }
+    }
+    format!("\x1b[{}m", converted.join(";"))
+}
+
+/// Returns the xterm 256-color palette index nearest to an RGB color: the
+/// closer of the nearest 6x6x6 color cube entry (16-231) and the nearest step
+/// of the grayscale ramp (232-255).
+fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let cube_step = |v: u8| match v {
+        0..48 => 0,
+        48..115 => 1,
+        _ => (v - 35) / 40,
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        [(r, r2), (g, g2), (b, b2)]
+            .iter()
+            .map(|&(a, b)| u32::from(a.abs_diff(b)).pow(2))
+            .sum::<u32>()
+    };
+
+    let (cr, cg, cb) = (cube_step(r), cube_step(g), cube_step(b));
+    let cube = (
+        LEVELS[usize::from(cr)],
+        LEVELS[usize::from(cg)],
+        LEVELS[usize::from(cb)],
+    );
+
+    // The average of three u8 values always fits in a u8
+    #[allow(clippy::cast_possible_truncation)]
+    let average = ((u32::from(r) + u32::from(g) + u32::from(b)) / 3) as u8;
+    let gray_step = if average > 238 {
+        23
+    } else {
+        average.saturating_sub(3) / 10
+    };
+    let gray = 8 + gray_step * 10;
+
+    if distance((gray, gray, gray)) < distance(cube) {
+        232 + gray_step
+    } else {
+        16 + 36 * cr + 6 * cg + cb
+    }
+}
+
+/// Parses the digits of an `rgb` or `rrggbb` hex color into its channels.
+fn parse_hex(hex: &str) -> Option<(u8, u8, u8)> {
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        3 => {
+            let double = |i: usize| channel(&hex.get(i..=i)?.repeat(2));
+            Some((double(0)?, double(1)?, double(2)?))
+        }
+        6 => Some((
+            channel(hex.get(0..2)?)?,
+            channel(hex.get(2..4)?)?,
+            channel(hex.get(4..6)?)?,
+        )),
+        _ => None,
+    }
+}
+
+/// A `--find` pattern compiled for highlighting names.
+///
+/// Whether a name matches is decided by the glob crate, as in
+/// [`TreeNode::prune_to_matching`](crate::TreeNode::prune_to_matching), so a
+/// name is highlighted exactly when the filter keeps it.
+#[derive(Debug, Clone)]
+struct HighlightPattern {
+    pattern: glob::Pattern,
+    /// For each literal run of the pattern: the part of the pattern from the
+    /// end of the previous run through this one, the rest of the pattern after
+    /// it, and the run's length in characters.
+    runs: Vec<(glob::Pattern, glob::Pattern, usize)>,
+}
+
+impl HighlightPattern {
+    /// Compiles `pattern`, or returns `None` if it isn't a valid glob.
+    fn new(pattern: &str) -> Option<Self> {
+        let whole = glob::Pattern::new(pattern).ok()?;
+        let mut runs = Vec::new();
+        let mut previous_end = 0;
+        for run in literal_runs(pattern) {
+            let piece = glob::Pattern::new(&pattern[previous_end..run.end]).ok()?;
+            let rest = glob::Pattern::new(&pattern[run.end..]).ok()?;
+            runs.push((piece, rest, pattern[run.clone()].chars().count()));
+            previous_end = run.end;
+        }
+        Some(Self {
+            pattern: whole,
+            runs,
+        })
+    }
+
+    /// Returns the byte ranges of `name` matched by the literal runs of the
+    /// pattern, or no spans if the pattern doesn't match `name` as a whole.
+    ///
+    /// Wildcards (`*`, `?`, `[...]`) are not part of any span. Each run takes
+    /// the earliest position that still lets the rest of the pattern match.
+    fn spans(&self, name: &str) -> Vec<Range<usize>> {
+        if !self.pattern.matches(name) {
+            return Vec::new();
+        }
+
+        let boundaries: Vec<usize> = name
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain([name.len()])
+            .collect();
+        let mut spans: Vec<Range<usize>> = Vec::new();
+        let mut start = 0;
+        for (piece, rest, len) in &self.runs {
+            let Some(end) = (start + len..boundaries.len()).find(|&end| {
+                piece.matches(&name[boundaries[start]..boundaries[end]])
+                    && rest.matches(&name[boundaries[end]..])
+            }) else {
+                return Vec::new();
+            };
+            let span = boundaries[end - len]..boundaries[end];
+            match spans.last_mut() {
+                Some(last) if last.end == span.start => last.end = span.end,
+                _ => spans.push(span),
+            }
+            start = end;
+        }
+        spans
+    }
+}
+
+/// Returns the byte ranges of the literal runs of the glob `pattern`: the text
+/// between its wildcards `*`, `?` and `[...]`. Classes are delimited the way
+/// the glob crate reads them, so `[]a]` and `[!]a]` are single classes.
+fn literal_runs(pattern: &str) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut run_start = None;
+    let mut wildcard_end = 0;
+    for (index, c) in pattern.char_indices() {
+        if index < wildcard_end {
+            continue;
+        }
+        let end = match c {
+            '*' | '?' => Some(index + 1),
+            '[' => {
+                // The first member, even a `]`, never closes the class
+                let first = index + 1 + usize::from(pattern[index + 1..].starts_with('!'));
+                let members = pattern[first..].char_indices().nth(1);
+                members.and_then(|(offset, _)| {
+                    pattern[first + offset..]
+                        .find(']')
+                        .map(|close| first + offset + close + 1)
+                })
+            }
+            _ => None,
+        };
+        match end {
+            Some(end) => {
+                if let Some(start) = run_start.take() {
+                    runs.push(start..index);
+                }
+                wildcard_end = end;
+            }
+            None => {
+                run_start.get_or_insert(index);
+            }
+        }
+    }
+    if let Some(start) = run_start {
+        runs.push(start..pattern.len());
+    }
+    runs
+}
 impl Default for ColorScheme {
     fn default() -> Self {
         Self::new()
@@ -176,6 +1036,7 @@ impl Default for ColorScheme {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::GlobRule;
 
     #[test]
     fn test_color_scheme_new() {
@@ -193,12 +1054,81 @@ mod tests {
         assert!(scheme.default_file.is_empty());
     }
 
+    #[test]
+    fn test_rgb_to_ansi256() {
+        // #ff8800 is the orange at cube position (5, 2, 0)
+        assert_eq!(rgb_to_ansi256(0xff, 0x88, 0x00), 208);
+        assert_eq!(rgb_to_ansi256(0, 0, 0), 16);
+        assert_eq!(rgb_to_ansi256(255, 255, 255), 231);
+        assert_eq!(rgb_to_ansi256(95, 215, 95), 77);
+        // Greys land on the grayscale ramp rather than the cube
+        assert_eq!(rgb_to_ansi256(0x80, 0x80, 0x80), 244);
+        assert_eq!(rgb_to_ansi256(0x1e, 0x2a, 0x3b), 236);
+    }
+
+    #[test]
+    fn test_to_ansi256() {
+        assert_eq!(to_ansi256("\x1b[1;38;2;255;136;0m"), "\x1b[1;38;5;208m");
+        assert_eq!(to_ansi256("\x1b[48;2;0;0;0m"), "\x1b[48;5;16m");
+        assert_eq!(to_ansi256("\x1b[1;31m"), "\x1b[1;31m");
+        assert_eq!(to_ansi256("custom"), "custom");
+    }
+
+    #[test]
+    fn test_theme256_converts_configured_colors() {
+        let config = ColorConfig {
+            folder: Some("#ff8800".to_string()),
+            extensions: HashMap::from([(".rs".to_string(), "#000000".to_string())]),
+            ..ColorConfig::default()
+        };
+        let mut scheme = ColorScheme::from_color_config(&config);
+        scheme.set_theme256(true);
+        scheme.set_guides_color(Some("#ffffff"));
+
+        assert_eq!(scheme.format_root("."), "\x1b[1;38;5;208m.\x1b[0m");
+        assert_eq!(
+            scheme.format_with_separator("└──", " ", "main.rs", false),
+            "\x1b[1;38;5;231m└──\x1b[0m \x1b[1;38;5;16mmain.rs\x1b[0m"
+        );
+        // Built-in basic colors are left alone
+        assert_eq!(
+            scheme.format_with_separator("", "", "notes.md", false),
+            "\x1b[1;36mnotes.md\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_file_color() {
+        let scheme = ColorScheme::new();
+        assert_eq!(scheme.file_color("main.rs"), Some("\x1b[1;31m"));
+        assert_eq!(scheme.file_color("Makefile"), Some("\x1b[1;34m"));
+        assert_eq!(ColorScheme::with_colors(false).file_color("main.rs"), None);
+    }
+
+    #[test]
+    fn test_write_with_color() {
+        let scheme = ColorScheme::new();
+        let mut out = Vec::new();
+        scheme
+            .write_with_color(&mut out, "├──", "main.rs", false)
+            .unwrap();
+        scheme
+            .write_with_color(&mut out, "└──", "src", true)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "├── \x1b[1;31mmain.rs\x1b[0m\n└── \x1b[1;37msrc\x1b[0m\n"
+        );
+    }
+
     #[test]
     fn test_parse_color() {
         assert_eq!(ColorScheme::parse_color("red"), "\x1b[1;31m");
         assert_eq!(ColorScheme::parse_color("green"), "\x1b[1;32m");
         assert_eq!(ColorScheme::parse_color("blue"), "\x1b[1;34m");
         assert_eq!(ColorScheme::parse_color("custom"), "custom");
+        assert_eq!(ColorScheme::parse_color("#0f0"), "\x1b[1;38;2;0;255;0m");
+        assert_eq!(ColorScheme::parse_color("#1e2a3b"), "\x1b[1;38;2;30;42;59m");
     }
 
     #[test]
@@ -253,19 +1183,439 @@ mod tests {
     }
 
     #[test]
-    fn test_print_with_color_enabled() {
+    fn test_dotfile_names_get_shell_color() {
         let scheme = ColorScheme::new();
-        // Should not panic
-        scheme.print_with_color("├──", "test.rs");
-        scheme.print_with_color("└──", "folder");
+        assert_eq!(scheme.get_color_code_for_file(".zshrc"), "\x1b[1;32m");
+        assert_eq!(scheme.get_color_code_for_file(".bashrc"), "\x1b[1;32m");
+        // Unlisted dotfiles still fall back to the default color
+        assert_eq!(
+            scheme.get_color_code_for_file(".gitconfig"),
+            scheme.default_file
+        );
     }
 
     #[test]
-    fn test_print_with_color_disabled() {
-        let scheme = ColorScheme::with_colors(false);
-        // Should not panic and output without colors
-        scheme.print_with_color("├──", "test.txt");
-        scheme.print_with_color("└──", "dir");
+    fn test_from_color_config_names() {
+        let mut config = ColorConfig::default();
+        config
+            .names
+            .insert("Makefile".to_string(), "yellow".to_string());
+        config
+            .names
+            .insert(".zshrc".to_string(), "cyan".to_string());
+
+        let scheme = ColorScheme::from_color_config(&config);
+        assert_eq!(scheme.get_color_code_for_file("Makefile"), "\x1b[1;33m");
+        assert_eq!(scheme.get_color_code_for_file(".zshrc"), "\x1b[1;36m");
+        assert_eq!(
+            scheme.format_at_depth("├──", " ", "Makefile", false, 1, None),
+            "├── \x1b[1;33mMakefile\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_from_color_config_glob_rules() {
+        let mut config = ColorConfig::default();
+        config.glob_rules.push(GlobRule {
+            pattern: "*_test.rs".to_string(),
+            color: "magenta".to_string(),
+        });
+
+        let scheme = ColorScheme::from_color_config(&config);
+        assert_eq!(
+            scheme.get_color_code_for_file("parser_test.rs"),
+            "\x1b[1;35m"
+        );
+        // Plain .rs files keep the extension color
+        assert_eq!(scheme.get_color_code_for_file("parser.rs"), "\x1b[1;31m");
+    }
+
+    #[test]
+    fn test_from_color_config_invalid_glob_is_skipped() {
+        let mut config = ColorConfig::default();
+        config.glob_rules.push(GlobRule {
+            pattern: "[".to_string(),
+            color: "magenta".to_string(),
+        });
+
+        let scheme = ColorScheme::from_color_config(&config);
+        assert!(scheme.glob_colors.is_empty());
+    }
+
+    #[test]
+    fn test_format_at_depth_alternates_folder_colors() {
+        let config = ColorConfig {
+            folder: Some("white".to_string()),
+            folder_alt: Some("yellow".to_string()),
+            ..ColorConfig::default()
+        };
+        let mut scheme = ColorScheme::from_color_config(&config);
+
+        // Disabled by default: every depth uses the folder color
+        assert_eq!(
+            scheme.format_at_depth("├──", " ", "src", true, 1, None),
+            "├── \x1b[1;37msrc\x1b[0m"
+        );
+
+        scheme.set_alternate_folders(true);
+        assert_eq!(
+            scheme.format_at_depth("├──", " ", "src", true, 1, None),
+            "├── \x1b[1;33msrc\x1b[0m"
+        );
+        assert_eq!(
+            scheme.format_at_depth("│   ├──", " ", "nested", true, 2, None),
+            "│   ├── \x1b[1;37mnested\x1b[0m"
+        );
+        assert_eq!(
+            scheme.format_at_depth("│   │   ├──", " ", "deeper", true, 3, None),
+            "│   │   ├── \x1b[1;33mdeeper\x1b[0m"
+        );
+
+        // Files keep their own colors at any depth
+        assert_eq!(
+            scheme.format_at_depth("├──", " ", "main.rs", false, 1, None),
+            "├── \x1b[1;31mmain.rs\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_from_color_config_only_skips_builtin_rules() {
+        let mut config = ColorConfig::default();
+        config
+            .extensions
+            .insert(".rs".to_string(), "green".to_string());
+
+        let scheme = ColorScheme::from_color_config_only(&config);
+        assert_eq!(scheme.get_color_code_for_file("main.rs"), "\x1b[1;32m");
+        // No built-in red for .py: falls back to the default file color
+        assert_eq!(scheme.get_color_code_for_file("script.py"), "\x1b[1;34m");
+        assert_eq!(scheme.get_color_code_for_file(".zshrc"), "\x1b[1;34m");
+        assert_eq!(scheme.folder, "\x1b[1;37m");
+    }
+
+    #[test]
+    fn test_hash_colors_are_stable_per_extension() {
+        let mut scheme = ColorScheme::new();
+        scheme.set_hash_colors(true);
+
+        let lua = scheme.get_color_code_for_file("init.lua");
+        assert_eq!(lua, scheme.get_color_code_for_file("plugins.lua"));
+        assert_ne!(lua, scheme.get_color_code_for_file("config.nix"));
+        assert!(HASH_PALETTE.contains(&lua));
+
+        // Configured and built-in colors take precedence over hashed ones
+        assert_eq!(scheme.get_color_code_for_file("main.rs"), "\x1b[1;31m");
+        // Dotfiles without an extension keep the default color
+        assert_eq!(
+            scheme.get_color_code_for_file(".gitignore"),
+            scheme.default_file
+        );
+    }
+
+    #[test]
+    fn test_age_bucket() {
+        let thresholds = [10, 100];
+        assert_eq!(age_bucket(0, &thresholds), 0);
+        assert_eq!(age_bucket(10, &thresholds), 0);
+        assert_eq!(age_bucket(11, &thresholds), 1);
+        assert_eq!(age_bucket(1000, &thresholds), 2);
+    }
+
+    #[test]
+    fn test_age_color_runs_from_green_to_grey() {
+        let mut scheme = ColorScheme::new();
+        assert_eq!(scheme.age_color(0), None);
+
+        scheme.set_age_buckets(1000, &[100, 10]);
+        assert_eq!(
+            scheme.age_color(995).as_deref(),
+            Some("\x1b[1;38;2;95;215;95m")
+        );
+        assert_eq!(
+            scheme.age_color(950).as_deref(),
+            Some("\x1b[1;38;2;101;161;101m")
+        );
+        assert_eq!(
+            scheme.age_color(0).as_deref(),
+            Some("\x1b[1;38;2;108;108;108m")
+        );
+
+        let mut plain = ColorScheme::with_colors(false);
+        plain.set_age_buckets(1000, &[10]);
+        assert_eq!(plain.age_color(0), None);
+    }
+
+    #[test]
+    fn test_newer_color_highlights_only_newer_files() {
+        let mut scheme = ColorScheme::new();
+        assert_eq!(scheme.newer_color("main.rs", 2000), None);
+
+        scheme.set_newer_than(Some(1000));
+        assert_eq!(
+            scheme.newer_color("main.rs", 1001).as_deref(),
+            Some("\x1b[1;31m\x1b[7m")
+        );
+        assert_eq!(scheme.newer_color("main.rs", 1000), None);
+
+        let mut plain = ColorScheme::with_colors(false);
+        plain.set_newer_than(Some(1000));
+        assert_eq!(plain.newer_color("main.rs", 2000), None);
+    }
+
+    #[test]
+    fn test_dim_dot_wraps_only_leading_dot() {
+        let mut scheme = ColorScheme::new();
+        scheme.set_dim_dot(true);
+
+        assert_eq!(
+            scheme.format_with_separator("├──", " ", ".gitignore", false),
+            "├── \x1b[2m.\x1b[0m\x1b[1;34mgitignore\x1b[0m"
+        );
+        assert_eq!(
+            scheme.format_with_separator("├──", " ", ".zshrc", false),
+            "├── \x1b[2m.\x1b[0m\x1b[1;32mzshrc\x1b[0m"
+        );
+        // Names without a leading dot are unchanged
+        assert_eq!(
+            scheme.format_with_separator("├──", " ", "main.rs", false),
+            "├── \x1b[1;31mmain.rs\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_dim_dot_without_colors() {
+        let mut scheme = ColorScheme::with_colors(false);
+        scheme.set_dim_dot(true);
+
+        assert_eq!(
+            scheme.format_with_separator("├──", " ", ".gitignore", false),
+            "├── .gitignore"
+        );
+    }
+
+    #[test]
+    fn test_format_dimmed() {
+        let scheme = ColorScheme::with_colors(true);
+        assert_eq!(
+            scheme.format_dimmed("└──", " ", "empty"),
+            "└── \x1b[2mempty\x1b[0m"
+        );
+
+        let plain = ColorScheme::with_colors(false);
+        assert_eq!(plain.format_dimmed("└──", " ", "empty"), "└── empty");
+    }
+
+    /// Returns the spans `HighlightPattern` highlights in `name`.
+    fn glob_match_spans(pattern: &str, name: &str) -> Vec<Range<usize>> {
+        HighlightPattern::new(pattern).unwrap().spans(name)
+    }
+
+    #[test]
+    fn test_literal_runs() {
+        assert_eq!(literal_runs("*.rs"), vec![1..4]);
+        assert_eq!(literal_runs("m?in.[rt]s"), vec![0..1, 2..5, 9..10]);
+        assert_eq!(literal_runs("[]a]b"), vec![4..5]);
+        assert_eq!(literal_runs("x[!]a]"), vec![0..1]);
+        assert_eq!(literal_runs("é*ü"), vec![0..2, 3..5]);
+    }
+
+    #[test]
+    fn test_glob_match_spans_agree_with_glob_crate() {
+        let patterns = [
+            "[!a]*.rs",
+            "[a-c]?.md",
+            "[]x].txt",
+            "[!]]*.sh",
+            "[!a-c]b*",
+            "*[0-9].log",
+            "[*]x",
+        ];
+        let names = [
+            "main.rs", "a.rs", "b1.md", "d1.md", "].txt", "x.txt", "].sh", "a.sh", "db", "ab",
+            "app9.log", "app.log", "*x", "ax",
+        ];
+        for pattern in patterns {
+            let glob = glob::Pattern::new(pattern).unwrap();
+            for name in names {
+                // Every pattern has a literal run, so a match always highlights something
+                assert_eq!(
+                    !glob_match_spans(pattern, name).is_empty(),
+                    glob.matches(name),
+                    "{pattern} against {name}"
+                );
+            }
+        }
+
+        assert_eq!(glob_match_spans("[!]]*.sh", "a.sh"), vec![1..4]);
+        // Patterns the glob crate rejects highlight nothing
+        assert!(HighlightPattern::new("[ab").is_none());
+    }
+
+    #[test]
+    fn test_glob_match_spans() {
+        assert_eq!(glob_match_spans("*.rs", "main.rs"), vec![4..7]);
+        assert_eq!(glob_match_spans("main*", "main.rs"), vec![0..4]);
+        assert_eq!(glob_match_spans("*test*", "my_test.rs"), vec![3..7]);
+        assert_eq!(
+            glob_match_spans("m?in.[rt]s", "main.rs"),
+            vec![0..1, 2..5, 6..7]
+        );
+        assert!(glob_match_spans("*.py", "main.rs").is_empty());
+        // Each `*` takes the shortest run that lets the rest match
+        assert_eq!(glob_match_spans("*a*", "banana"), vec![1..2]);
+    }
+
+    #[test]
+    fn test_glob_match_spans_many_stars_stays_fast() {
+        let name = format!("{}b", "a".repeat(60));
+        assert_eq!(glob_match_spans("*a*a*a*a*a*a*a*a*a*b", &name).len(), 2);
+        assert!(glob_match_spans("*a*a*a*a*a*a*a*a*a*c", &name).is_empty());
+    }
+
+    #[test]
+    fn test_highlight_wraps_matched_substring() {
+        let mut scheme = ColorScheme::new();
+        scheme.set_highlight_pattern(Some("src/*.rs"));
+
+        assert_eq!(
+            scheme.format_with_separator("├──", " ", "main.rs", false),
+            "├── \x1b[1;31mmain\x1b[0m\x1b[1;31m\x1b[7m.rs\x1b[0m"
+        );
+        // Names the pattern doesn't match are left alone
+        assert_eq!(
+            scheme.format_with_separator("├──", " ", "src", true),
+            "├── \x1b[1;37msrc\x1b[0m"
+        );
+        // Directories are never highlighted, even when their name matches
+        assert_eq!(
+            scheme.format_at_depth("├──", " ", "lib.rs", true, 1, None),
+            "├── \x1b[1;37mlib.rs\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_parse_background() {
+        assert_eq!(
+            ColorScheme::parse_background("#222").as_deref(),
+            Some("\x1b[48;2;34;34;34m")
+        );
+        assert_eq!(
+            ColorScheme::parse_background("#1e2a3b").as_deref(),
+            Some("\x1b[48;2;30;42;59m")
+        );
+        assert_eq!(
+            ColorScheme::parse_background("blue").as_deref(),
+            Some("\x1b[44m")
+        );
+        assert_eq!(ColorScheme::parse_background("#12"), None);
+        assert_eq!(ColorScheme::parse_background("#ggg"), None);
+    }
+
+    #[test]
+    fn test_top_dir_color_cycles() {
+        let scheme = ColorScheme::new();
+        let mut colors: Vec<String> = (0..12).filter_map(|i| scheme.top_dir_color(i)).collect();
+        colors.sort();
+        colors.dedup();
+        assert_eq!(colors.len(), 12);
+        assert_eq!(scheme.top_dir_color(12), scheme.top_dir_color(0));
+        assert!(ColorScheme::with_colors(false).top_dir_color(0).is_none());
+    }
+
+    #[test]
+    fn test_file_category() {
+        assert_eq!(file_category("install.sh"), "scripts");
+        assert_eq!(file_category(".zshrc"), "scripts");
+        assert_eq!(file_category("config.toml"), "configs");
+        assert_eq!(file_category("README.md"), "docs");
+        assert_eq!(file_category("main.rs"), "source");
+        assert_eq!(file_category("Makefile"), "other");
+    }
+
+    #[test]
+    fn test_format_legend_lists_present_categories() {
+        let scheme = ColorScheme::new();
+        let legend = scheme
+            .format_legend(&["src/main.rs", "README.md", "docs/guide.md", "Makefile"])
+            .unwrap();
+
+        assert_eq!(
+            legend,
+            "\x1b[1;36m■\x1b[0m docs  \x1b[1;31m■\x1b[0m source  \x1b[1;34m■\x1b[0m other"
+        );
+        assert!(
+            ColorScheme::with_colors(false)
+                .format_legend(&["main.rs"])
+                .is_none()
+        );
+        assert!(scheme.format_legend::<&str>(&[]).is_none());
+    }
+
+    #[test]
+    fn test_guides_color_applies_to_prefix_only() {
+        let mut scheme = ColorScheme::new();
+        scheme.set_guides_color(Some("magenta"));
+
+        assert_eq!(
+            scheme.format_at_depth("│   ├──", " ", "main.rs", false, 2, None),
+            "\x1b[1;35m│   ├──\x1b[0m \x1b[1;31mmain.rs\x1b[0m"
+        );
+
+        let mut plain = ColorScheme::with_colors(false);
+        plain.set_guides_color(Some("magenta"));
+        assert_eq!(
+            plain.format_at_depth("│   ├──", " ", "main.rs", false, 2, None),
+            "│   ├── main.rs"
+        );
+    }
+
+    #[test]
+    fn test_line_background_brackets_full_line() {
+        let config: crate::Config = toml::from_str(
+            r##"
+[colors]
+line-background = "#222"
+"##,
+        )
+        .unwrap();
+        let mut scheme = ColorScheme::from_color_config(&config.colors);
+
+        let bg = "\x1b[48;2;34;34;34m";
+        assert_eq!(
+            scheme.format_with_separator("│   ├──", " ", "main.rs", false),
+            format!("{bg}│   ├── \x1b[1;31mmain.rs\x1b[0m")
+        );
+        assert_eq!(scheme.format_root("."), format!("{bg}\x1b[1;37m.\x1b[0m"));
+
+        // Internal resets re-apply the background so it spans the whole line
+        scheme.set_dim_dot(true);
+        assert_eq!(
+            scheme.format_with_separator("└──", " ", ".zshrc", false),
+            format!("{bg}└── \x1b[2m.\x1b[0m{bg}\x1b[1;32mzshrc\x1b[0m")
+        );
+    }
+
+    #[test]
+    fn test_format_root() {
+        assert_eq!(ColorScheme::new().format_root("."), "\x1b[1;37m.\x1b[0m");
+        assert_eq!(ColorScheme::with_colors(false).format_root("."), ".");
+    }
+
+    #[test]
+    fn test_format_with_separator() {
+        let plain = ColorScheme::with_colors(false);
+        assert_eq!(
+            plain.format_with_separator("├──", " ", "a.rs", false),
+            "├── a.rs"
+        );
+        assert_eq!(plain.format_root("."), ".");
+
+        let colored = ColorScheme::new();
+        assert_eq!(
+            colored.format_with_separator("└──", " ", "dir", true),
+            "└── \x1b[1;37mdir\x1b[0m"
+        );
+        assert_eq!(colored.format_root("."), "\x1b[1;37m.\x1b[0m");
     }
 
     #[test]
@@ -317,11 +1667,15 @@ mod tests {
     fn test_color_scheme_folder_vs_file() {
         let scheme = ColorScheme::new();
 
-        // Files with dots get file colors
-        scheme.print_with_color("", "test.txt");
-
-        // Files without dots get folder colors
-        scheme.print_with_color("", "folder");
+        // The caller's is_dir decides, not whether the name has a dot
+        assert_eq!(
+            scheme.format_with_separator("├──", " ", "Makefile", false),
+            "├── \x1b[1;34mMakefile\x1b[0m"
+        );
+        assert_eq!(
+            scheme.format_with_separator("├──", " ", "my.config", true),
+            "├── \x1b[1;37mmy.config\x1b[0m"
+        );
     }
 
     #[test]
@@ -347,6 +1701,49 @@ mod tests {
         assert_eq!(scheme.default_file, "\x1b[1;35m");
     }
 
+    #[test]
+    fn test_set_theme_palettes() {
+        for theme in [Theme::Deuteranopia, Theme::Protanopia] {
+            let mut scheme = ColorScheme::new();
+            scheme.set_theme(theme);
+
+            let codes: Vec<&str> = ["run.sh", "a.toml", "a.md", "a.rs", "a.plist", "a.bin"]
+                .iter()
+                .map(|name| scheme.get_color_code_for_file(name))
+                .collect();
+            assert_eq!(codes, theme.palette());
+            assert_eq!(scheme.get_color_code_for_file(".zshrc"), codes[0]);
+
+            // Every category stays distinguishable
+            let mut distinct = codes.clone();
+            distinct.sort_unstable();
+            distinct.dedup();
+            assert_eq!(distinct.len(), codes.len());
+        }
+
+        assert_eq!(
+            Theme::Deuteranopia.palette()[3],
+            "\x1b[1;4;38;5;220m",
+            "source files are underlined"
+        );
+    }
+
+    #[test]
+    fn test_set_theme_keeps_configured_colors() {
+        let config = ColorConfig {
+            extensions: HashMap::from([(".rs".to_string(), "magenta".to_string())]),
+            ..ColorConfig::default()
+        };
+        let mut scheme = ColorScheme::from_color_config(&config);
+        scheme.set_theme(Theme::Protanopia);
+        assert_eq!(scheme.get_color_code_for_file("a.rs"), "\x1b[1;35m");
+        assert_eq!(scheme.get_color_code_for_file("a.md"), "\x1b[38;5;153m");
+
+        let mut disabled = ColorScheme::with_colors(false);
+        disabled.set_theme(Theme::Protanopia);
+        assert_eq!(disabled.get_color_code_for_file("a.md"), "");
+    }
+
     #[test]
     fn test_default_trait() {
         let scheme1 = ColorScheme::default();
@@ -1,7 +1,8 @@
 //! Configuration module for file filtering.
 //!
 //! This module handles loading and parsing configuration from a TOML file
-//! located at `~/.config/chezmoi/chezmoi-files.toml`.
+//! located at `~/.config/chezmoi/chezmoi-files.toml`. When that file is absent,
+//! a `[data.files]` table in chezmoi's own `chezmoi.toml` is used instead.
 //!
 //! # Examples
 //!
@@ -19,16 +20,19 @@
 //! let default_config = Config::default();
 //! ```
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
+use std::ffi::OsString;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 
 /// Configuration for file filtering.
 ///
 /// This struct contains lists of files to exclude and include when processing paths.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Config {
     /// List of files to exclude from the tree visualization.
     #[serde(rename = "excluded-files", default)]
@@ -39,10 +43,24 @@ pub struct Config {
     /// Color configuration.
     #[serde(default)]
     pub colors: ColorConfig,
+    /// Input handling, overridden by the matching command-line flags.
+    #[serde(default, skip_serializing_if = "InputConfig::is_default")]
+    pub input: InputConfig,
+    /// Output defaults, overridden by the matching command-line flags.
+    #[serde(default, skip_serializing_if = "OutputConfig::is_default")]
+    pub output: OutputConfig,
+    /// Per-directory overrides of the `--sort` order.
+    #[serde(
+        rename = "sort-rules",
+        alias = "sort_rules",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub sort_rules: Vec<SortRule>,
 }
 
 /// A list of file patterns.
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Default)]
 pub struct FileList {
     /// The file patterns to match against.
     #[serde(default)]
@@ -50,21 +68,254 @@ pub struct FileList {
 }
 
 /// Color configuration for the tree output.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ColorConfig {
     /// Whether colors are enabled.
     #[serde(default = "default_true")]
     pub enabled: bool,
     /// Color for folders.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub folder: Option<String>,
+    /// Color for directories at odd depths when alternating folder colors.
+    #[serde(
+        rename = "folder-alt",
+        alias = "folder_alt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub folder_alt: Option<String>,
     /// Default color for files.
-    #[serde(rename = "default-file")]
+    #[serde(rename = "default-file", skip_serializing_if = "Option::is_none")]
     pub default_file: Option<String>,
+    /// Color for the part of a name matched by `--find`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<String>,
+    /// Background color for whole rendered lines (e.g. `#222`).
+    #[serde(
+        rename = "line-background",
+        alias = "line_background",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub line_background: Option<String>,
     /// Colors for specific file extensions.
     #[serde(default)]
     pub extensions: HashMap<String, String>,
+    /// Colors for exact file names (e.g. `.zshrc`), checked before extensions.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub names: HashMap<String, String>,
+    /// Colors for file names matching glob patterns, checked before extensions.
+    #[serde(
+        rename = "glob-rules",
+        alias = "glob_rules",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub glob_rules: Vec<GlobRule>,
 }
 
+/// Input handling settings.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InputConfig {
+    /// Whether the current directory is stripped from the start of input paths.
+    #[serde(rename = "strip-pwd", alias = "strip_pwd", default = "default_true")]
+    pub strip_pwd: bool,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self { strip_pwd: true }
+    }
+}
+
+impl InputConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Output defaults for the tree.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct OutputConfig {
+    /// The default `--output` format: `tree`, `yaml`, `json`, `json-paths`, `tsv`, `flat-depth` or `ndjson`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+impl OutputConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A rule coloring files whose name matches a glob pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GlobRule {
+    /// The glob pattern matched against the file name (e.g. `*_test.rs`).
+    pub pattern: String,
+    /// The color to use for matching files.
+    pub color: String,
+}
+
+/// A rule overriding the sort order for directories whose path matches a glob.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SortRule {
+    /// The glob pattern matched against the directory path (e.g. `src` or `src/**`).
+    pub path: String,
+    /// The sort order for the directory's children: `name`, `type`, `type-name`, `files-first`, or `none`.
+    pub mode: String,
+}
+
+/// How the configuration file was resolved by [`Config::load`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigStatus {
+    /// The configuration file was found and parsed successfully.
+    Loaded,
+    /// The configuration file exists but is empty; defaults are used.
+    Empty,
+    /// No configuration file exists; the `[data.files]` table of chezmoi's
+    /// `chezmoi.toml` was used instead.
+    Embedded,
+    /// No configuration file exists; defaults are used.
+    Missing,
+    /// The configuration file could not be read; defaults are used.
+    Unreadable(String),
+    /// The configuration file could not be parsed; defaults are used.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Loaded => write!(f, "loaded"),
+            Self::Embedded => write!(f, "not found, using [data.files] from chezmoi.toml"),
+            Self::Empty => write!(f, "empty, using defaults"),
+            Self::Missing => write!(f, "not found, using defaults"),
+            Self::Unreadable(e) => write!(f, "unreadable ({e}), using defaults"),
+            Self::Invalid(e) => write!(f, "invalid ({}), using defaults", e.trim()),
+        }
+    }
+}
+
+/// Where the effective value of a configuration setting came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// The built-in default.
+    Default,
+    /// The user's `chezmoi-files.toml`.
+    User,
+    /// The `[data.files]` table of chezmoi's `chezmoi.toml`.
+    Chezmoi,
+    /// A command-line flag.
+    Cli,
+    /// An environment variable such as `NO_COLOR`.
+    Env,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::User => write!(f, "user"),
+            Self::Chezmoi => write!(f, "chezmoi.toml"),
+            Self::Cli => write!(f, "CLI"),
+            Self::Env => write!(f, "environment"),
+        }
+    }
+}
+
+/// The source that won for each setting category, in a fixed order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigSources(Vec<(&'static str, ConfigSource)>);
+
+impl ConfigSources {
+    /// Setting categories, each with the table and keys (including aliases)
+    /// that set it in a config file.
+    const CATEGORIES: [(&'static str, Option<&'static str>, &'static [&'static str]); 10] = [
+        ("excluded-files", None, &["excluded-files"]),
+        ("included-files", None, &["included-files"]),
+        ("sort-rules", None, &["sort-rules", "sort_rules"]),
+        ("colors.enabled", Some("colors"), &["enabled"]),
+        (
+            "colors.palette",
+            Some("colors"),
+            &[
+                "folder",
+                "folder-alt",
+                "folder_alt",
+                "default-file",
+                "highlight",
+                "line-background",
+                "line_background",
+            ],
+        ),
+        ("colors.extensions", Some("colors"), &["extensions"]),
+        ("colors.names", Some("colors"), &["names"]),
+        (
+            "colors.glob-rules",
+            Some("colors"),
+            &["glob-rules", "glob_rules"],
+        ),
+        (
+            "input.strip-pwd",
+            Some("input"),
+            &["strip-pwd", "strip_pwd"],
+        ),
+        ("output.format", Some("output"), &["format"]),
+    ];
+
+    /// Attributes every category to `source`.
+    fn all(source: ConfigSource) -> Self {
+        Self(
+            Self::CATEGORIES
+                .iter()
+                .map(|&(category, _, _)| (category, source))
+                .collect(),
+        )
+    }
+
+    /// Attributes each category set in `table` to `source`, and the rest to the defaults.
+    fn from_table(table: &toml::Table, source: ConfigSource) -> Self {
+        Self(
+            Self::CATEGORIES
+                .iter()
+                .map(|&(category, parent, keys)| {
+                    let scope = parent.map_or(Some(table), |parent| {
+                        table.get(parent).and_then(toml::Value::as_table)
+                    });
+                    let set =
+                        scope.is_some_and(|scope| keys.iter().any(|key| scope.contains_key(*key)));
+                    (category, if set { source } else { ConfigSource::Default })
+                })
+                .collect(),
+        )
+    }
+
+    /// Records that `source` overrode `category`. Unknown categories are ignored.
+    pub fn set(&mut self, category: &str, source: ConfigSource) {
+        if let Some(entry) = self.0.iter_mut().find(|(name, _)| *name == category) {
+            entry.1 = source;
+        }
+    }
+
+    /// Returns the source that won for `category`, if it is a known category.
+    #[must_use]
+    pub fn get(&self, category: &str) -> Option<ConfigSource> {
+        self.0
+            .iter()
+            .find(|(name, _)| *name == category)
+            .map(|&(_, source)| source)
+    }
+
+    /// Iterates over the categories and their winning sources.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, ConfigSource)> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+/// Wildcard-free exclusions written by 0.7.0's `config --init`, which relied
+/// on substring matching, and the globs that still exclude the same files.
+const LEGACY_PATTERNS: [(&str, &str); 2] =
+    [("DS_Store", "*DS_Store"), (".zcompdump", ".zcompdump*")];
+
 const fn default_true() -> bool {
     true
 }
@@ -74,8 +325,13 @@ impl Default for ColorConfig {
         Self {
             enabled: true,
             folder: None,
+            folder_alt: None,
             default_file: None,
+            highlight: None,
+            line_background: None,
             extensions: HashMap::new(),
+            names: HashMap::new(),
+            glob_rules: Vec::new(),
         }
     }
 }
@@ -88,13 +344,13 @@ impl Config {
     ///
     /// # Default Exclusions
     ///
-    /// - `DS_Store`
+    /// - `*DS_Store`
     /// - `fish_variables*`
     /// - `.rubocop.yml`
     /// - `.ruff_cache`
     /// - `yazi.toml-`
     /// - `.zcompcache`
-    /// - `.zcompdump`
+    /// - `.zcompdump*`
     /// - `.zsh_history`
     /// - `plugins/fish`
     /// - `plugins/zsh`
@@ -108,33 +364,135 @@ impl Config {
     /// ```
     #[must_use]
     pub fn new() -> Self {
+        Self::load().0
+    }
+
+    /// Loads the configuration file and reports how it was resolved.
+    ///
+    /// Behaves like [`Config::new`], but also returns a [`ConfigStatus`] describing
+    /// whether the file was found and parsed or whether defaults were used instead.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use chezmoi_files::Config;
+    ///
+    /// let (config, status) = Config::load();
+    /// eprintln!("config: {status}");
+    /// ```
+    #[must_use]
+    pub fn load() -> (Self, ConfigStatus) {
+        let (config, status, _) = Self::load_with_sources();
+        (config, status)
+    }
+
+    /// Loads the configuration file like [`Config::load`], and also reports
+    /// which source each setting category came from.
+    #[must_use]
+    pub fn load_with_sources() -> (Self, ConfigStatus, ConfigSources) {
         let config_path = Self::config_path();
+        let defaults = |status| {
+            (
+                Self::default(),
+                status,
+                ConfigSources::all(ConfigSource::Default),
+            )
+        };
 
         match fs::read_to_string(&config_path) {
-            Ok(content) if !content.trim().is_empty() => match toml::from_str(&content) {
-                Ok(config) => config,
+            Ok(content) if content.trim().is_empty() => defaults(ConfigStatus::Empty),
+            Ok(content) => match toml::from_str(&content) {
+                Ok(config) => {
+                    Self::warn_legacy_patterns(&config, &config_path);
+                    let table = toml::from_str(&content).unwrap_or_default();
+                    let sources = ConfigSources::from_table(&table, ConfigSource::User);
+                    (config, ConfigStatus::Loaded, sources)
+                }
                 Err(e) => {
                     eprintln!(
                         "Warning: failed to parse config file {}: {e}",
                         config_path.display()
                     );
-                    Self::default()
+                    defaults(ConfigStatus::Invalid(e.to_string()))
                 }
             },
-            _ => Self::default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Self::load_embedded().unwrap_or_else(|| defaults(ConfigStatus::Missing))
+            }
+            Err(e) => defaults(ConfigStatus::Unreadable(e.to_string())),
+        }
+    }
+
+    /// Loads the `[data.files]` table from chezmoi's own config file, if present.
+    fn load_embedded() -> Option<(Self, ConfigStatus, ConfigSources)> {
+        let table = Self::chezmoi_data_files()?;
+
+        match table.clone().try_into() {
+            Ok(config) => {
+                Self::warn_legacy_patterns(&config, &Self::chezmoi_config_path());
+                let sources = ConfigSources::from_table(&table, ConfigSource::Chezmoi);
+                Some((config, ConfigStatus::Embedded, sources))
+            }
+            Err(e) => {
+                eprintln!(
+                    "Warning: failed to parse [data.files] in {}: {e}",
+                    Self::chezmoi_config_path().display()
+                );
+                Some((
+                    Self::default(),
+                    ConfigStatus::Invalid(e.to_string()),
+                    ConfigSources::all(ConfigSource::Default),
+                ))
+            }
         }
     }
 
+    /// Returns the `[data.files]` table of chezmoi's own config file, or `None`
+    /// if the file is missing, isn't TOML or has no such table.
+    #[must_use]
+    pub fn chezmoi_data_files() -> Option<toml::Table> {
+        let content = fs::read_to_string(Self::chezmoi_config_path()).ok()?;
+        Self::data_files_table(&content)
+    }
+
+    /// Parses the `[data.files]` table of `chezmoi.toml`'s contents into a config.
+    #[cfg(test)]
+    fn from_chezmoi_toml(content: &str) -> Option<Result<Self, toml::de::Error>> {
+        Self::data_files_table(content).map(toml::Table::try_into)
+    }
+
+    /// Extracts the `[data.files]` table from the contents of `chezmoi.toml`.
+    ///
+    /// Returns `None` when the content isn't TOML or has no such table. `[data]`
+    /// holds free-form template data, so a `files` value there that isn't a
+    /// table is the user's own variable and is ignored. Only the keys this crate
+    /// knows are read from the table; the rest of chezmoi's config is ignored.
+    fn data_files_table(content: &str) -> Option<toml::Table> {
+        let chezmoi: toml::Table = toml::from_str(content).ok()?;
+        chezmoi.get("data")?.get("files")?.as_table().cloned()
+    }
+
     /// Returns the path to the configuration file.
     ///
-    /// Uses `~/.config/chezmoi/chezmoi-files.toml` as the standard location.
+    /// Uses `$CHEZMOI_FILES/config.toml` if `CHEZMOI_FILES` is set, and
+    /// otherwise `chezmoi-files.toml` in chezmoi's config directory,
+    /// `$XDG_CONFIG_HOME/chezmoi` or `~/.config/chezmoi`.
     #[must_use]
     pub fn config_path() -> PathBuf {
-        let home = env::var("HOME").unwrap_or_else(|_| String::from("."));
-        PathBuf::from(home)
-            .join(".config")
-            .join("chezmoi")
-            .join("chezmoi-files.toml")
+        resolve_config_path(
+            env::var_os("CHEZMOI_FILES"),
+            env::var_os("XDG_CONFIG_HOME"),
+            env::var_os("HOME"),
+        )
+    }
+
+    /// Returns the path to chezmoi's own configuration file.
+    ///
+    /// Uses `chezmoi.toml` in chezmoi's config directory, `$XDG_CONFIG_HOME/chezmoi`
+    /// or `~/.config/chezmoi`. `CHEZMOI_FILES` doesn't move it.
+    #[must_use]
+    pub fn chezmoi_config_path() -> PathBuf {
+        chezmoi_config_dir(env::var_os("XDG_CONFIG_HOME"), env::var_os("HOME")).join("chezmoi.toml")
     }
 
     /// Returns the default configuration as a TOML string.
@@ -142,7 +500,7 @@ impl Config {
     /// This is useful for creating a default configuration file.
     #[must_use]
     pub fn default_config_toml() -> String {
-        r#"# Configuration for chezmoi-files
+        r##"# Configuration for chezmoi-files
 # Edit this file to customize which files are excluded from the tree visualization
 
 [excluded-files]
@@ -152,13 +510,13 @@ impl Config {
 #   "cache/*"      - matches any file in a cache directory
 #   "test_*.rs"    - matches test_foo.rs, test_bar.rs, etc.
 files = [
-    "DS_Store",
+    "*DS_Store",
     "fish_variables*",
     ".rubocop.yml",
     ".ruff_cache",
     "yazi.toml-*",
     ".zcompcache",
-    ".zcompdump",
+    ".zcompdump*",
     ".zsh_history",
     "plugins/fish",
     "plugins/zsh",
@@ -176,17 +534,66 @@ enabled = true
 # Available colors: black, red, green, yellow, blue, magenta, cyan, white
 # You can also use custom ANSI codes like "\x1b[1;32m"
 # folder = "white"
+# folder-alt = "cyan"   # used for odd-depth folders with --alt-folders
 # default-file = "blue"
+# highlight = "\x1b[7m"  # part of a name matched by --find (default: reverse video)
+# line-background = "#222"  # background for whole lines: #rgb, #rrggbb, or a color name
 
 # Customize colors for specific file extensions
 # [colors.extensions]
 # ".rs" = "red"
 # ".py" = "green"
 # ".md" = "cyan"
-"#
+
+# Customize colors for exact file names (checked before extensions)
+# [colors.names]
+# ".zshrc" = "green"
+# "Makefile" = "yellow"
+
+# Customize colors for file names matching glob patterns (checked before extensions)
+# [[colors.glob-rules]]
+# pattern = "*_test.rs"
+# color = "magenta"
+
+# Keep the current directory on input paths instead of stripping it (like --no-strip)
+# [input]
+# strip-pwd = false
+
+# Default --output format (tree, yaml, json, json-paths, tsv, flat-depth or ndjson)
+# [output]
+# format = "tree"
+
+# Override the --sort order for directories matching a glob
+# [[sort-rules]]
+# path = "src"
+# mode = "type"
+"##
         .to_string()
     }
 
+    /// Serializes the configuration to a TOML string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be represented as TOML.
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    /// Writes the configuration to `path` as TOML.
+    ///
+    /// The file is written atomically with [`write_atomic`]. The parent
+    /// directory must already exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be serialized or the file
+    /// cannot be written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let toml = self.to_toml().map_err(io::Error::other)?;
+        write_atomic(path, &toml)
+    }
+
     /// Checks if a path matches any exclusion pattern using glob matching.
     ///
     /// # Arguments
@@ -204,6 +611,35 @@ enabled = true
             .any(|pattern| Self::matches_glob(path, pattern))
     }
 
+    /// Returns the exclusions of this config that 0.7.0 wrote as plain
+    /// substrings, each paired with the glob that matches what it used to.
+    ///
+    /// Patterns without wildcards now match whole path components, so the old
+    /// `DS_Store` default no longer excludes `.DS_Store`.
+    #[must_use]
+    pub fn legacy_patterns(&self) -> Vec<(&str, &'static str)> {
+        self.excluded_files
+            .files
+            .iter()
+            .filter_map(|pattern| {
+                LEGACY_PATTERNS
+                    .iter()
+                    .find(|(legacy, _)| legacy == pattern)
+                    .map(|&(_, replacement)| (pattern.as_str(), replacement))
+            })
+            .collect()
+    }
+
+    /// Warns about each of the config's [`legacy_patterns`](Self::legacy_patterns).
+    fn warn_legacy_patterns(config: &Self, path: &Path) {
+        for (pattern, replacement) in config.legacy_patterns() {
+            eprintln!(
+                "Warning: exclusion {pattern:?} in {} only matches whole names now; replace it with {replacement:?} to keep excluding what it did in 0.7.0",
+                path.display()
+            );
+        }
+    }
+
     /// Checks if a path matches any inclusion pattern using glob matching.
     ///
     /// # Arguments
@@ -224,23 +660,35 @@ enabled = true
     /// Matches a path against a glob pattern.
     ///
     /// Supports wildcards: `*`, `?`, `[abc]`, `[a-z]`
+    ///
+    /// A pattern of `n` components (e.g. `cache/*` has two) matches when any run
+    /// of `n` consecutive path components matches it, so `*.tmp` matches the
+    /// last component of `a/b/c.tmp` and `plugins/fish` matches anywhere in the
+    /// path. Wildcards never match across a `/`. Patterns without wildcards, or
+    /// that aren't valid globs, must equal the components literally, so `fish`
+    /// doesn't match `selfish.txt`.
     fn matches_glob(path: &str, pattern: &str) -> bool {
-        // If pattern contains glob characters, use glob matching
-        if (pattern.contains('*') || pattern.contains('?') || pattern.contains('['))
-            && let Ok(glob_pattern) = glob::Pattern::new(pattern)
-        {
-            // Try matching the full path
-            if glob_pattern.matches(path) {
-                return true;
-            }
-            // Also try matching any component of the path
-            return path
-                .split('/')
-                .any(|component| glob_pattern.matches(component));
-        }
+        let pattern = pattern.trim_matches('/');
+        let glob_pattern =
+            (pattern.contains('*') || pattern.contains('?') || pattern.contains('['))
+                .then(|| glob::Pattern::new(pattern).ok())
+                .flatten();
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::new()
+        };
 
-        // Fall back to substring matching
-        path.contains(pattern)
+        let components: Vec<&str> = path.split('/').collect();
+        components
+            .windows(pattern.split('/').count())
+            .map(|window| window.join("/"))
+            .any(|candidate| {
+                glob_pattern
+                    .as_ref()
+                    .map_or(candidate == pattern, |glob_pattern| {
+                        glob_pattern.matches_with(&candidate, options)
+                    })
+            })
     }
 }
 
@@ -249,13 +697,13 @@ impl Default for Config {
         Self {
             excluded_files: FileList {
                 files: vec![
-                    "DS_Store",
+                    "*DS_Store",
                     "fish_variables*",
                     ".rubocop.yml",
                     ".ruff_cache",
                     "yazi.toml-*",
                     ".zcompcache",
-                    ".zcompdump",
+                    ".zcompdump*",
                     ".zsh_history",
                     "plugins/fish",
                     "plugins/zsh",
@@ -266,14 +714,130 @@ impl Default for Config {
             },
             included_files: FileList { files: Vec::new() },
             colors: ColorConfig::default(),
+            input: InputConfig::default(),
+            output: OutputConfig::default(),
+            sort_rules: Vec::new(),
         }
     }
 }
 
+/// Resolves [`Config::config_path`] from the values of `CHEZMOI_FILES`,
+/// `XDG_CONFIG_HOME` and `HOME`. Empty values count as unset.
+fn resolve_config_path(
+    chezmoi_files: Option<OsString>,
+    xdg_config_home: Option<OsString>,
+    home: Option<OsString>,
+) -> PathBuf {
+    chezmoi_files.filter(|dir| !dir.is_empty()).map_or_else(
+        || chezmoi_config_dir(xdg_config_home, home).join("chezmoi-files.toml"),
+        |dir| PathBuf::from(dir).join("config.toml"),
+    )
+}
+
+/// Returns chezmoi's config directory: `$XDG_CONFIG_HOME/chezmoi` when that is
+/// an absolute path, as the XDG spec requires, and `$HOME/.config/chezmoi` otherwise.
+fn chezmoi_config_dir(xdg_config_home: Option<OsString>, home: Option<OsString>) -> PathBuf {
+    xdg_config_home
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| {
+            let home = home.filter(|home| !home.is_empty());
+            PathBuf::from(home.unwrap_or_else(|| ".".into())).join(".config")
+        })
+        .join("chezmoi")
+}
+
+/// Writes `contents` to `path` atomically.
+///
+/// The contents go to a temporary file next to `path`, which is then renamed
+/// over it, so an interrupted write never leaves a truncated file behind. The
+/// parent directory must already exist.
+///
+/// # Errors
+///
+/// Returns an error if the temporary file cannot be written or renamed.
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = fs::File::create(&temp_path).and_then(|mut file| {
+        io::Write::write_all(&mut file, contents.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = result.and_then(|()| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_from_chezmoi_toml_reads_data_files_table() {
+        let content = r#"
+[git]
+autoCommit = true
+
+[data]
+email = "me@example.com"
+
+[data.files.excluded-files]
+files = ["*.bak"]
+
+[data.files.colors]
+folder = "cyan"
+"#;
+        let config = Config::from_chezmoi_toml(content).unwrap().unwrap();
+
+        assert_eq!(config.excluded_files.files, vec!["*.bak"]);
+        assert_eq!(config.colors.folder.as_deref(), Some("cyan"));
+        assert!(config.is_excluded("notes.bak"));
+    }
+
+    #[test]
+    fn test_config_sources_from_table() {
+        let table: toml::Table = toml::from_str(
+            r#"
+sort_rules = []
+
+[colors.extensions]
+".rs" = "red"
+"#,
+        )
+        .unwrap();
+        let mut sources = ConfigSources::from_table(&table, ConfigSource::User);
+
+        assert_eq!(sources.get("colors.extensions"), Some(ConfigSource::User));
+        assert_eq!(sources.get("sort-rules"), Some(ConfigSource::User));
+        assert_eq!(sources.get("colors.names"), Some(ConfigSource::Default));
+        assert_eq!(sources.get("excluded-files"), Some(ConfigSource::Default));
+
+        sources.set("excluded-files", ConfigSource::Cli);
+        assert_eq!(sources.get("excluded-files"), Some(ConfigSource::Cli));
+        assert_eq!(sources.get("no-such-category"), None);
+    }
+
+    #[test]
+    fn test_from_chezmoi_toml_without_table() {
+        assert!(Config::from_chezmoi_toml("[data]\nemail = \"me@example.com\"\n").is_none());
+        assert!(Config::from_chezmoi_toml("not toml [").is_none());
+        // A template variable named `files` is not this crate's config
+        assert!(Config::from_chezmoi_toml("[data]\nfiles = [\"x\"]\n").is_none());
+        assert!(
+            Config::from_chezmoi_toml("[data.files]\nsort-rules = 3\n")
+                .unwrap()
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_matches_glob_simple_substring() {
         assert!(Config::matches_glob("path/to/DS_Store", "DS_Store"));
@@ -281,6 +845,62 @@ mod tests {
         assert!(!Config::matches_glob("foo/baz", "bar"));
     }
 
+    #[test]
+    fn test_legacy_patterns() {
+        let mut config = Config::default();
+        assert!(config.legacy_patterns().is_empty());
+
+        config.excluded_files.files = vec![
+            "DS_Store".to_string(),
+            "*.bak".to_string(),
+            ".zcompdump".to_string(),
+        ];
+        assert_eq!(
+            config.legacy_patterns(),
+            vec![("DS_Store", "*DS_Store"), (".zcompdump", ".zcompdump*")]
+        );
+
+        // The replacements exclude what the substrings used to
+        assert!(!Config::matches_glob("a/.DS_Store", "DS_Store"));
+        assert!(Config::matches_glob("a/.DS_Store", "*DS_Store"));
+        assert!(!Config::matches_glob(".zcompdump-host", ".zcompdump"));
+        assert!(Config::matches_glob(".zcompdump-host", ".zcompdump*"));
+    }
+
+    #[test]
+    fn test_matches_glob_literal_patterns_match_whole_components() {
+        assert!(!Config::matches_glob("selfish.txt", "fish"));
+        assert!(!Config::matches_glob("fishing/rod.txt", "fish"));
+        assert!(Config::matches_glob(".config/fish/config.fish", "fish"));
+        assert!(Config::matches_glob(
+            "a/plugins/fish/init.fish",
+            "plugins/fish"
+        ));
+        assert!(!Config::matches_glob(
+            "a/plugins/fishy/init.fish",
+            "plugins/fish"
+        ));
+    }
+
+    #[test]
+    fn test_matches_glob_real_globs() {
+        // *.tmp matches the name anywhere, but * never crosses a directory
+        assert!(Config::matches_glob("a/b/test.tmp", "*.tmp"));
+        assert!(!Config::matches_glob("src/lib/main.rs", "src*.rs"));
+        assert!(!Config::matches_glob("file.tmpl", "*.tmp"));
+
+        // cache/* matches files directly in any cache directory
+        assert!(Config::matches_glob("cache/data.bin", "cache/*"));
+        assert!(Config::matches_glob("home/cache/data.bin", "cache/*"));
+        assert!(!Config::matches_glob("mycache/data.bin", "cache/*"));
+        assert!(!Config::matches_glob("cache", "cache/*"));
+
+        // [a-z].rs matches a single lowercase letter before the extension
+        assert!(Config::matches_glob("src/x.rs", "[a-z].rs"));
+        assert!(!Config::matches_glob("src/xy.rs", "[a-z].rs"));
+        assert!(!Config::matches_glob("src/X.rs", "[a-z].rs"));
+    }
+
     #[test]
     fn test_matches_glob_wildcard() {
         assert!(Config::matches_glob(
@@ -357,12 +977,62 @@ mod tests {
         let _ = config.colors.enabled;
     }
 
+    #[test]
+    fn test_config_status_display() {
+        assert_eq!(ConfigStatus::Loaded.to_string(), "loaded");
+        assert_eq!(
+            ConfigStatus::Missing.to_string(),
+            "not found, using defaults"
+        );
+        assert!(
+            ConfigStatus::Invalid("bad key".to_string())
+                .to_string()
+                .contains("bad key")
+        );
+    }
+
     #[test]
     fn test_config_path() {
         let path = Config::config_path();
         assert!(path.to_string_lossy().contains("chezmoi-files.toml"));
     }
 
+    #[test]
+    fn test_resolve_config_path_precedence() {
+        let home = || Some(OsString::from("/home/me"));
+
+        assert_eq!(
+            resolve_config_path(None, None, home()),
+            PathBuf::from("/home/me/.config/chezmoi/chezmoi-files.toml")
+        );
+        assert_eq!(
+            resolve_config_path(None, Some("/xdg".into()), home()),
+            PathBuf::from("/xdg/chezmoi/chezmoi-files.toml")
+        );
+        // Relative and empty XDG_CONFIG_HOME values are ignored
+        assert_eq!(
+            resolve_config_path(None, Some("xdg".into()), home()),
+            PathBuf::from("/home/me/.config/chezmoi/chezmoi-files.toml")
+        );
+        assert_eq!(
+            resolve_config_path(
+                Some(String::new().into()),
+                Some(String::new().into()),
+                home()
+            ),
+            PathBuf::from("/home/me/.config/chezmoi/chezmoi-files.toml")
+        );
+        // CHEZMOI_FILES wins over both
+        assert_eq!(
+            resolve_config_path(Some("/custom".into()), Some("/xdg".into()), home()),
+            PathBuf::from("/custom/config.toml")
+        );
+        assert_eq!(
+            chezmoi_config_dir(Some("/xdg".into()), home()),
+            PathBuf::from("/xdg/chezmoi")
+        );
+    }
+
     #[test]
     fn test_default_config_toml() {
         let toml = Config::default_config_toml();
@@ -372,6 +1042,81 @@ mod tests {
         assert!(toml.contains("DS_Store"));
     }
 
+    #[test]
+    fn test_input_strip_pwd_parsing() {
+        assert!(Config::default().input.strip_pwd);
+        assert!(!Config::default().to_toml().unwrap().contains("[input]"));
+
+        let config: Config = toml::from_str("[input]\nstrip-pwd = false\n").unwrap();
+        assert!(!config.input.strip_pwd);
+        assert!(config.to_toml().unwrap().contains("strip-pwd = false"));
+
+        let config: Config = toml::from_str("[input]\nstrip_pwd = false\n").unwrap();
+        assert!(!config.input.strip_pwd);
+        let config: Config = toml::from_str("[input]\n").unwrap();
+        assert!(config.input.strip_pwd);
+    }
+
+    #[test]
+    fn test_output_format_parsing() {
+        let config: Config = toml::from_str("[output]\nformat = \"json\"\n").unwrap();
+        assert_eq!(config.output.format.as_deref(), Some("json"));
+
+        // The section is left out of the TOML until it is set
+        assert!(!Config::default().to_toml().unwrap().contains("[output]"));
+        assert!(config.to_toml().unwrap().contains("[output]"));
+    }
+
+    #[test]
+    fn test_to_toml_round_trip() {
+        let mut config = Config::default();
+        config.colors.folder = Some("cyan".to_string());
+
+        let toml = config.to_toml().unwrap();
+        assert!(toml.contains("[excluded-files]"));
+        assert!(toml.contains("folder = \"cyan\""));
+
+        let parsed: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.excluded_files.files, config.excluded_files.files);
+        assert_eq!(parsed.colors.folder, config.colors.folder);
+    }
+
+    #[test]
+    fn test_save_and_reload_round_trip() {
+        let dir = env::temp_dir().join(format!("chezmoi-files-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chezmoi-files.toml");
+
+        let mut config = Config::default();
+        config.included_files.files.push("keep.me".to_string());
+        config.colors.folder = Some("cyan".to_string());
+        config
+            .colors
+            .extensions
+            .insert(".rs".to_string(), "green".to_string());
+        config
+            .colors
+            .names
+            .insert(".zshrc".to_string(), "yellow".to_string());
+        config.colors.glob_rules.push(GlobRule {
+            pattern: "*_test.rs".to_string(),
+            color: "magenta".to_string(),
+        });
+        config.sort_rules.push(SortRule {
+            path: "src".to_string(),
+            mode: "type".to_string(),
+        });
+
+        config.save(&path).unwrap();
+        let reloaded: Config = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(reloaded, config);
+
+        // No temporary file is left behind
+        assert!(!dir.join("chezmoi-files.toml.tmp").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_file_list_default() {
         let file_list = FileList::default();
@@ -387,6 +1132,74 @@ mod tests {
         assert_eq!(color_config.extensions.len(), 0);
     }
 
+    #[test]
+    fn test_color_names_parsing() {
+        let config: Config = toml::from_str(
+            r#"
+[colors.names]
+".zshrc" = "green"
+"Makefile" = "yellow"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.colors.names.len(), 2);
+        assert_eq!(config.colors.names[".zshrc"], "green");
+    }
+
+    #[test]
+    fn test_sort_rules_parsing() {
+        let config: Config = toml::from_str(
+            r#"
+[[sort-rules]]
+path = "src"
+mode = "type"
+
+[[sort-rules]]
+path = "docs/**"
+mode = "none"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.sort_rules.len(), 2);
+        assert_eq!(config.sort_rules[0].path, "src");
+        assert_eq!(config.sort_rules[0].mode, "type");
+        assert_eq!(config.sort_rules[1].path, "docs/**");
+        assert_eq!(config.sort_rules[1].mode, "none");
+    }
+
+    #[test]
+    fn test_color_glob_rules_parsing() {
+        let config: Config = toml::from_str(
+            r#"
+[[colors.glob-rules]]
+pattern = "*_test.rs"
+color = "magenta"
+
+[[colors.glob-rules]]
+pattern = "Dockerfile*"
+color = "cyan"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.colors.glob_rules.len(), 2);
+        assert_eq!(config.colors.glob_rules[0].pattern, "*_test.rs");
+        assert_eq!(config.colors.glob_rules[0].color, "magenta");
+
+        // The snake_case spelling is accepted as well
+        let config: Config = toml::from_str(
+            r#"
+[[colors.glob_rules]]
+pattern = "*_test.rs"
+color = "magenta"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.colors.glob_rules.len(), 1);
+    }
+
     #[test]
     fn test_matches_glob_path_components() {
         // Test that patterns match path components, not just the full path
@@ -396,7 +1209,7 @@ mod tests {
 
     #[test]
     fn test_matches_glob_invalid_pattern() {
-        // Invalid glob patterns should fall back to substring matching
+        // Invalid glob patterns fall back to literal matching
         assert!(Config::matches_glob("test[file", "test[file"));
     }
 
@@ -36,6 +36,9 @@
 //!
 //! // Create color scheme
 //! let color_scheme = ColorScheme::new();
+//!
+//! // Render the tree to a string
+//! let text = chezmoi_files::render(&root, &color_scheme);
 //! ```
 //!
 //! ## Features
@@ -54,7 +57,7 @@
 //! ```toml
 //! [excluded-files]
 //! files = [
-//!     "DS_Store",
+//!     "*DS_Store",
 //!     "*.tmp",
 //!     "cache/*",
 //! ]
@@ -75,9 +78,14 @@
 // Re-export main modules
 pub mod color;
 pub mod config;
+pub mod render;
 pub mod tree;
 
 // Re-export commonly used types
-pub use color::ColorScheme;
-pub use config::{ColorConfig, Config, FileList};
-pub use tree::{TreeDepth, TreeNode, TreeParams, TreePart, TreeTrunk};
+pub use color::{ColorScheme, Theme};
+pub use config::{
+    ColorConfig, Config, ConfigSource, ConfigSources, ConfigStatus, FileList, GlobRule,
+    InputConfig, OutputConfig, SortRule,
+};
+pub use render::{PartCounts, RenderOptions, TreeRenderer, render, render_to};
+pub use tree::{TreeCounts, TreeDepth, TreeNode, TreeParams, TreePart, TreeTrunk};
@@ -4,19 +4,27 @@
 //! It reads file paths from stdin, filters them based on configurable rules, and outputs
 //! a hierarchical tree structure with syntax-highlighted file names.
 
-use chezmoi_files::{ColorScheme, TreeDepth, TreeNode, TreeParams, TreeTrunk, config};
-use clap::Parser;
+use chezmoi_files::{ColorScheme, RenderOptions, Theme, TreeDepth, TreeNode, TreeRenderer, config};
+use clap::{Parser, ValueEnum};
+use std::collections::{BTreeMap, HashSet};
 use std::env;
+use std::fmt::Write as _;
+use std::fs;
 use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// A command-line utility that generates colorized tree visualizations of file paths.
 ///
 /// Reads file paths from stdin, filters them based on configurable rules, and outputs
 /// a hierarchical tree structure with syntax-highlighted file names.
 #[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)] // CLI flags map naturally to bools
 #[command(name = "chezmoi-files")]
 #[command(version)]
 #[command(about, long_about = None)]
+#[command(after_help = "Run `chezmoi-files examples` for common usage examples.")]
 struct Args {
     #[command(subcommand)]
     command: Option<Command>,
@@ -25,13 +33,310 @@ struct Args {
     #[arg(long, global = true)]
     no_color: bool,
 
-    /// Show statistics (file and directory counts)
-    #[arg(long, short, global = true)]
-    stats: bool,
-
-    /// Sort order: name, type, or none
+    /// When to color output; `always` and `never` override `[colors] enabled` in the config
+    #[arg(
+        long,
+        value_name = "WHEN",
+        default_value = "auto",
+        global = true,
+        conflicts_with = "no_color"
+    )]
+    color: ColorChoice,
+
+    /// Show statistics (file and directory counts); `--stats=include-excluded`
+    /// also breaks down the excluded paths by extension
+    #[arg(
+        long,
+        short,
+        global = true,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "basic"
+    )]
+    stats: Option<StatsMode>,
+
+    /// With --stats, also report how many entries sit at each depth
+    #[arg(long, requires = "stats")]
+    by_depth: bool,
+
+    /// Sort order: name, type, type-name, files-first, or none
     #[arg(long, value_name = "ORDER", default_value = "none", global = true)]
     sort: SortOrder,
+
+    /// Sort every directory by name (byte order), ignoring input order and sort rules
+    #[arg(long, conflicts_with = "sort")]
+    reproducible: bool,
+
+    /// Within each directory, list files after all subdirectories (applied after --sort)
+    #[arg(long, global = true)]
+    files_last: bool,
+
+    /// Show only directories; files are left out of the tree and of the --stats counts
+    #[arg(long)]
+    dirs_only: bool,
+
+    /// Don't print notes such as `(no input)` to stderr
+    #[arg(long, short)]
+    quiet: bool,
+
+    /// Print diagnostics about the resolved configuration to stderr
+    #[arg(long, short, global = true)]
+    verbose: bool,
+
+    /// Color files with an extension, as `EXT=COLOR` (can be repeated)
+    #[arg(long, value_name = "EXT=COLOR", value_parser = parse_color_ext)]
+    color_ext: Vec<(String, String)>,
+
+    /// Additional exclusion pattern (can be repeated)
+    #[arg(long, value_name = "PATTERN", global = true)]
+    exclude: Vec<String>,
+
+    /// Additional inclusion pattern (can be repeated)
+    #[arg(long, value_name = "PATTERN", global = true)]
+    include: Vec<String>,
+
+    /// Print the effective configuration as TOML and exit
+    #[arg(long)]
+    dump_config: bool,
+
+    /// Print which source (default, user config, chezmoi.toml or CLI) set each config category to stderr
+    #[arg(long)]
+    show_sources: bool,
+
+    /// Print a legend of the file-category colors used in the tree after it
+    #[arg(long)]
+    legend: bool,
+
+    /// Report files that share the same name across directories
+    #[arg(long)]
+    duplicate_names: bool,
+
+    /// Print only the duplicate-names report, without the tree
+    #[arg(long)]
+    duplicate_names_only: bool,
+
+    /// Print each file's full path, one per line in tree order, instead of the tree
+    #[arg(long, visible_alias = "flat", conflicts_with_all = ["output", "duplicate_names_only"])]
+    files_only: bool,
+
+    /// Warn on stderr about directories with more than N direct children
+    #[arg(long, value_name = "N", conflicts_with = "stream")]
+    max_siblings_warn: Option<usize>,
+
+    /// Stop after N rendered lines and print a truncation notice
+    #[arg(long, value_name = "N", conflicts_with = "stream")]
+    max_total_lines: Option<usize>,
+
+    /// Assume a terminal N columns wide: longer lines are cut off with `…`, so
+    /// output is the same wherever it is rendered
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(1..),
+        conflicts_with = "compact_colors"
+    )]
+    render_width: Option<u16>,
+
+    /// Text placed between the tree connector and the entry name
+    #[arg(long, value_name = "STRING", default_value = " ")]
+    separator: String,
+
+    /// Omit the newline after the final line of output
+    #[arg(long)]
+    no_trailing_newline: bool,
+
+    /// Leave out the `.` root line of tree output (other formats never include it)
+    #[arg(long)]
+    null_root: bool,
+
+    /// Text prepended, uncolored, to every line of output
+    #[arg(long, value_name = "STRING", default_value = "")]
+    line_prefix: String,
+
+    /// Print only the connector prefix of each row, without names or colors
+    #[arg(long)]
+    print_prefix_only: bool,
+
+    /// Print the time spent reading input, building the tree and rendering to stderr
+    #[arg(long)]
+    timing: bool,
+
+    /// Print how many of each tree part were rendered to stderr
+    #[arg(long, hide = true)]
+    debug_parts: bool,
+
+    /// Merge chains of single-child directories into one `a/b/c` label
+    #[arg(long)]
+    collapse: bool,
+
+    /// Show file names without their extension, still colored by it
+    #[arg(long)]
+    strip_extensions: bool,
+
+    /// Elide the middle of labels longer than N characters with `…`
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    max_label_width: Option<u16>,
+
+    /// Render at most N levels below the root; deeper directories show a count of hidden entries
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    max_depth: Option<u16>,
+
+    /// Print a blank line between the subtrees of top-level entries
+    #[arg(long)]
+    group_separator: bool,
+
+    /// Keep the color on across consecutive entries of the same color instead of
+    /// resetting it after each one; the connectors between them take that color
+    #[arg(long)]
+    compact_colors: bool,
+
+    /// Draw the tree with ASCII connectors when the locale isn't UTF-8
+    #[arg(long)]
+    ascii_safe_fallback: bool,
+
+    /// Resolve `.` and `..` components in each input path before building the tree
+    #[arg(long)]
+    normalize: bool,
+
+    /// Output format: tree, yaml, json, json-paths, tsv, flat-depth or ndjson [default: `[output] format`
+    /// in the config, or tree]
+    #[arg(long, value_name = "FORMAT")]
+    output: Option<OutputFormat>,
+
+    /// Pretty-print JSON output instead of writing it on one line
+    #[arg(long)]
+    pretty: bool,
+
+    /// With --output tsv, start with a `depth type name path` header row
+    #[arg(long)]
+    tsv_header: bool,
+
+    /// Input format: lines (one path per line), json (as written by --output json)
+    /// or timestamped (`MTIME<TAB>PATH` lines, e.g. from `find -printf '%T@\t%p\n'`)
+    #[arg(long, value_name = "FORMAT", default_value = "lines")]
+    input_format: InputFormat,
+
+    /// Take the path from the Nth whitespace-separated field of each line
+    /// (1-based; negative counts from the end, e.g. -1 for `ls -l` output)
+    #[arg(long, value_name = "N", allow_negative_numbers = true, value_parser = parse_field)]
+    field: Option<i32>,
+
+    /// Read paths from `chezmoi managed` for the given source directory instead of stdin
+    #[arg(long, value_name = "DIR", conflicts_with = "input_format")]
+    from_chezmoi_source: Option<PathBuf>,
+
+    /// Use only the configured color rules, not the built-in extension groups
+    #[arg(long)]
+    no_default_colors: bool,
+
+    /// Dim the leading dot of dotfile names
+    #[arg(long)]
+    dim_dot: bool,
+
+    /// Give files with unconfigured extensions a stable color hashed from the extension
+    #[arg(long)]
+    hash_colors: bool,
+
+    /// Convert hex colors to the nearest 256-color palette entry, for terminals without truecolor
+    #[arg(long)]
+    theme256: bool,
+
+    /// Use a palette preset for a color-vision deficiency
+    #[arg(long, value_enum, value_name = "THEME")]
+    theme: Option<ThemeChoice>,
+
+    /// Color the tree connectors with COLOR, independently of the names
+    #[arg(long, value_name = "COLOR")]
+    guides_color: Option<String>,
+
+    /// Highlight files whose timestamped mtime is newer than FILE's
+    #[arg(long, value_name = "FILE", conflicts_with = "age_colors")]
+    newer_than: Option<PathBuf>,
+
+    /// Give each top-level directory and everything below it its own color
+    #[arg(long)]
+    color_by_top_dir: bool,
+
+    /// Color files from green (new) to grey (old) by the mtimes of timestamped input
+    #[arg(long)]
+    age_colors: bool,
+
+    /// With --age-colors, comma-separated bucket boundaries in days
+    #[arg(
+        long,
+        value_name = "DAYS",
+        value_delimiter = ',',
+        default_value = "1,7,30,365",
+        requires = "age_colors"
+    )]
+    age_buckets: Vec<u64>,
+
+    /// Alternate directory colors by depth using the `folder-alt` color
+    #[arg(long)]
+    alt_folders: bool,
+
+    /// Dim the names of directories that contain no files, at any depth
+    #[arg(long)]
+    dim_empty_dirs: bool,
+
+    /// Tab characters in input lines: keep them in names, or strip them
+    #[arg(long, value_name = "MODE", default_value = "keep")]
+    tabs: TabMode,
+
+    /// Decompress gzip input (gzip data is also detected by its magic bytes)
+    #[arg(long)]
+    gzip: bool,
+
+    /// Skip input lines longer than N bytes
+    #[arg(long, value_name = "N", default_value_t = 64 * 1024)]
+    max_line_bytes: usize,
+
+    /// Show only paths matching a glob, plus the directories leading to them
+    #[arg(long, value_name = "GLOB")]
+    find: Option<String>,
+
+    /// Print only the number of paths in the tree, without the tree
+    #[arg(long)]
+    count_only: bool,
+
+    /// Keep the current directory on input paths instead of stripping it
+    /// (overrides `[input] strip-pwd` in the config)
+    #[arg(long)]
+    no_strip: bool,
+
+    /// Use the first input line as the root label; later paths are relative to it
+    #[arg(long, conflicts_with_all = ["input_format", "stream"])]
+    first_line_root: bool,
+
+    /// Comma-separated directories to show even when no input path falls inside them
+    #[arg(long, value_name = "DIRS", value_delimiter = ',')]
+    seed_dirs: Vec<String>,
+
+    /// Treat input paths ending in `/` as directories, even when nothing is listed inside them
+    #[arg(long)]
+    no_trim_trailing_slash: bool,
+
+    /// Keep absolute paths outside the current directory under their own top-level
+    /// subtree, named after their first component (e.g. `/etc`)
+    #[arg(long)]
+    multi_root: bool,
+
+    /// Render top-level entries as soon as input moves past them (requires --sort none)
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "duplicate_names",
+            "duplicate_names_only",
+            "count_only",
+            "find",
+            "files_last",
+            "dirs_only",
+            "files_only",
+            "by_depth",
+        ]
+    )]
+    stream: bool,
 }
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
@@ -42,6 +347,81 @@ enum SortOrder {
     Name,
     /// Sort by type (directories first, then by extension)
     Type,
+    /// Directories first (by name), then files grouped by extension, then by name
+    TypeName,
+    /// Files first, then directories, each group by name
+    FilesFirst,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// Colorized tree drawn with box characters
+    Tree,
+    /// Nested YAML mappings; files map to null
+    Yaml,
+    /// Nested JSON objects; files map to null
+    Json,
+    /// A flat JSON array of sorted file paths
+    JsonPaths,
+    /// One `depth<TAB>type<TAB>name<TAB>path` row per entry
+    Tsv,
+    /// One `depth<TAB>path` line per entry
+    FlatDepth,
+    /// One JSON object per entry per line, written as the tree is walked
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ColorChoice {
+    /// Follow `CLICOLOR_FORCE`, `NO_COLOR` and whether stdout is a terminal, then `[colors] enabled`
+    Auto,
+    /// Always color, even if the config disables colors
+    Always,
+    /// Never color
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ThemeChoice {
+    /// Distinguishable with reduced green sensitivity
+    Deuteranopia,
+    /// Distinguishable with reduced red sensitivity
+    Protanopia,
+}
+
+impl From<ThemeChoice> for Theme {
+    fn from(choice: ThemeChoice) -> Self {
+        match choice {
+            ThemeChoice::Deuteranopia => Self::Deuteranopia,
+            ThemeChoice::Protanopia => Self::Protanopia,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum StatsMode {
+    /// File, directory and excluded counts
+    Basic,
+    /// Also the number of input paths and the excluded paths per extension
+    IncludeExcluded,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum TabMode {
+    /// Keep tabs as part of names (tabs are valid in file names)
+    Keep,
+    /// Remove every tab from each input line
+    Strip,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum InputFormat {
+    /// One path per line
+    Lines,
+    /// Nested JSON objects, as written by `--output json`
+    Json,
+    /// `MTIME<TAB>PATH` per line, MTIME in seconds since the Unix epoch
+    Timestamped,
 }
 
 #[derive(Parser, Debug)]
@@ -55,15 +435,59 @@ enum Command {
         /// Initialize configuration file with defaults
         #[arg(long)]
         init: bool,
+
+        /// With --init, replace an existing file (the old one is kept as *.bak)
+        #[arg(long, requires = "init")]
+        force: bool,
     },
+    /// Print common usage examples
+    Examples,
 }
 
+/// Usage examples printed by the `examples` subcommand.
+const EXAMPLES: &str = "\
+# Show the files chezmoi manages as a tree
+chezmoi managed | chezmoi-files
+
+# Show any list of paths, e.g. everything below the current directory
+find . -type f | chezmoi-files
+
+# Write the tree as JSON (add --pretty to indent it)
+chezmoi managed | chezmoi-files --output json
+
+# Only show files matching a glob, highlighting the matched part
+chezmoi managed | chezmoi-files --find '*.toml'
+
+# Hide files on top of the configured exclusions
+chezmoi managed | chezmoi-files --exclude '*.bak' --exclude 'cache/*'
+
+# Plain output with file and directory counts, e.g. for logs
+chezmoi managed | chezmoi-files --no-color --stats
+";
+
 /// Statistics about the tree structure.
 #[derive(Default, Debug)]
 struct TreeStats {
     files: usize,
     directories: usize,
     excluded: usize,
+    /// Input paths that passed filtering, before `--find`.
+    included: usize,
+    /// Excluded paths per file extension (`(none)` for names without one).
+    excluded_by_extension: BTreeMap<String, usize>,
+}
+
+impl TreeStats {
+    /// Records a path removed by the exclusion rules.
+    fn record_excluded(&mut self, path: &str) {
+        self.excluded += 1;
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let extension = name
+            .get(1..)
+            .and_then(|rest| rest.rsplit_once('.'))
+            .map_or_else(|| "(none)".to_string(), |(_, ext)| format!(".{ext}"));
+        *self.excluded_by_extension.entry(extension).or_default() += 1;
+    }
 }
 
 /// The main function of the program.
@@ -81,92 +505,706 @@ struct TreeStats {
 /// echo "path/to/file" | cargo run
 /// ```
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if args.reproducible {
+        args.sort = SortOrder::Name;
+    }
 
     if let Some(ref command) = args.command {
         handle_command(command);
         return;
     }
 
-    if io::stdin().is_terminal() {
+    let (config, config_status) = load_config(&mut args);
+
+    if args.dump_config {
+        dump_config(&config);
+        return;
+    }
+
+    if args.from_chezmoi_source.is_none() && io::stdin().is_terminal() {
         eprintln!("No input provided. Please pipe data into the program.");
         return;
     }
 
-    let current_dir = env::current_dir().expect("Failed to get current directory");
-    let current_dir_str = current_dir
-        .to_str()
-        .expect("Failed to convert PathBuf to string");
-
-    let config = config::Config::new();
-    let color_enabled = !args.no_color && config.colors.enabled;
-    let color_scheme = ColorScheme::from_config(
-        color_enabled,
-        config.colors.folder.clone(),
-        config.colors.default_file.clone(),
-        config.colors.extensions.clone(),
-    );
+    let current_dir = pwd_prefix(&config);
+    let current_dir_str = current_dir.as_str();
+
+    let color_enabled = config.colors.enabled;
+
+    if args.verbose {
+        print_diagnostics(&args, &config, &config_status, color_enabled);
+    }
+
+    let color_scheme = build_color_scheme(&args, &config.colors);
+
+    let sort_overrides = if args.reproducible {
+        Vec::new()
+    } else {
+        compile_sort_rules(&config.sort_rules)
+    };
+    let streaming = args.stream
+        && matches!(args.sort, SortOrder::None)
+        && sort_overrides.is_empty()
+        && args.output == Some(OutputFormat::Tree)
+        && matches!(args.input_format, InputFormat::Lines);
+    if args.stream && !streaming {
+        eprintln!("Warning: --stream only applies to tree output from line input without sorting");
+    }
 
     let mut root = TreeNode::new();
     root.is_leaf = false;
+    for dir in &args.seed_dirs {
+        root.add_dir(dir.split('/').filter(|p| !p.is_empty()));
+    }
     let mut stats = TreeStats::default();
+    let render_options = render_options(&args);
+    let mut stdout = io::BufWriter::new(io::stdout().lock());
+    let mut printer = TreeRenderer::new(&mut stdout, &color_scheme, &render_options);
 
-    let stdin = io::stdin();
-    for line in stdin.lock().lines() {
-        let Ok(path) = line else {
-            eprintln!("Error reading line: {}", line.unwrap_err());
-            continue;
-        };
+    if streaming {
+        exit_on_write_error(printer.write_root("."));
+    }
 
-        match process_path(&path, current_dir_str, &config) {
-            PathResult::Included(relative_path) => {
-                root.add_path(relative_path.split('/').filter(|p| !p.is_empty()));
+    let mut timings = Timings::default();
+    let read_started = Instant::now();
+    let root_label = match args.input_format {
+        InputFormat::Lines | InputFormat::Timestamped => {
+            let stream_to = streaming.then_some(&mut printer);
+            read_line_input(
+                &args,
+                &config,
+                current_dir_str,
+                &mut root,
+                &mut stats,
+                stream_to,
+                &mut timings,
+            )
+        }
+        InputFormat::Json => {
+            read_json_input(
+                &mut root,
+                current_dir_str,
+                &config,
+                &mut stats,
+                &mut timings,
+            );
+            None
+        }
+    };
+
+    timings.read = read_started.elapsed().saturating_sub(timings.build);
+
+    if !args.quiet && root_label.is_none() && stats.included + stats.excluded == 0 {
+        eprintln!("(no input)");
+    }
+
+    let build_started = Instant::now();
+    if let Some(limit) = args.max_siblings_warn {
+        warn_wide_directories(&root, "", limit);
+    }
+    shape_tree(&args, &mut root, &sort_overrides);
+    count_tree(&root, &mut stats);
+    timings.build += build_started.elapsed();
+
+    let render_started = Instant::now();
+    let root_label = root_label.unwrap_or_else(|| ".".to_string());
+    exit_on_write_error(render_output(
+        &args,
+        &root,
+        &root_label,
+        &stats,
+        &mut printer,
+        streaming,
+    ));
+    timings.render = render_started.elapsed();
+
+    if args.timing {
+        timings.report();
+    }
+}
+
+/// Loads the configuration and layers the command-line overrides on top,
+/// settling `args.output` and printing `--show-sources` along the way.
+fn load_config(args: &mut Args) -> (config::Config, config::ConfigStatus) {
+    let (mut config, status, mut sources) = config::Config::load_with_sources();
+    apply_overrides(&mut config, &mut sources, args);
+    args.output = Some(resolve_output_format(args.output, &config.output));
+    if args.show_sources {
+        print_sources(&sources);
+    }
+    (config, status)
+}
+
+/// Prints the effective configuration as TOML for `--dump-config`.
+fn dump_config(config: &config::Config) {
+    match config.to_toml() {
+        Ok(toml) => print!("{toml}"),
+        Err(e) => eprintln!("Error serializing configuration: {e}"),
+    }
+}
+
+/// Applies `--find`, the sort order and `--files-last` to the finished tree.
+fn shape_tree(args: &Args, root: &mut TreeNode, sort_overrides: &[SortOverride]) {
+    if let Some(pattern) = &args.find
+        && let Err(e) = root.prune_to_matching(pattern)
+    {
+        eprintln!("Error: invalid --find pattern {pattern:?}: {e}");
+        process::exit(1);
+    }
+
+    // Apply sorting if requested
+    if !matches!(args.sort, SortOrder::None) || !sort_overrides.is_empty() {
+        sort_tree(root, args.sort, sort_overrides);
+    }
+    if args.files_last {
+        move_files_last(root);
+    }
+    if args.dirs_only {
+        remove_files(root);
+    }
+}
+
+/// Writes the selected output for the finished tree.
+///
+/// When `streaming`, the root line and completed entries were already printed
+/// while reading input.
+fn render_output(
+    args: &Args,
+    root: &TreeNode,
+    root_label: &str,
+    stats: &TreeStats,
+    printer: &mut Printer,
+    streaming: bool,
+) -> io::Result<()> {
+    if args.count_only {
+        printer.line(&stats.files.to_string())?;
+        return printer.flush();
+    }
+
+    if args.files_only {
+        print_file_paths(root, printer)?;
+        return printer.flush();
+    }
+
+    if args.output == Some(OutputFormat::Ndjson) {
+        write_ndjson(root, "", 1, printer)?;
+        printer.truncation_notice(root.node_count())?;
+        return printer.flush();
+    }
+
+    if let Some(serialized) = serialize_tree(args, root) {
+        match serialized {
+            Ok(text) => {
+                for line in text.lines() {
+                    printer.line(line)?;
+                }
+                printer.truncation_notice(text.lines().count())?;
+                printer.flush()?;
             }
-            PathResult::Excluded => {
-                stats.excluded += 1;
+            Err(e) => eprintln!("Error serializing tree: {e}"),
+        }
+        return Ok(());
+    }
+
+    if args.duplicate_names_only {
+        print_duplicate_names(root, printer)?;
+        return printer.flush();
+    }
+
+    if streaming {
+        printer.write_children(root, TreeDepth::root().deeper())?;
+        printer.finish_colors()?;
+        let root_lines = usize::from(!args.null_root);
+        printer.truncation_notice(root_lines + root.node_count())?;
+    } else {
+        printer.write_tree(root_label, root)?;
+    }
+
+    print_summary(args, root, stats, printer)
+}
+
+/// The renderer all tree command output goes through, to buffered stdout.
+type Printer<'a> = TreeRenderer<'a, io::BufWriter<io::StdoutLock<'static>>>;
+
+/// Ends the program when output can no longer be written, e.g. to a closed pipe.
+fn exit_on_write_error(result: io::Result<()>) {
+    if let Err(e) = result {
+        eprintln!("Error writing output: {e}");
+        process::exit(1);
+    }
+}
+
+/// Serializes the tree in the machine-readable `--output` format, or returns
+/// `None` for tree and line-by-line NDJSON output.
+fn serialize_tree(args: &Args, root: &TreeNode) -> Option<Result<String, String>> {
+    let serialized = match args.output.unwrap_or(OutputFormat::Tree) {
+        OutputFormat::Tree | OutputFormat::Ndjson => return None,
+        OutputFormat::Yaml => serde_yaml::to_string(root).map_err(|e| e.to_string()),
+        OutputFormat::Json => to_json(root, args.pretty),
+        OutputFormat::JsonPaths => {
+            let mut paths = root.leaf_paths();
+            paths.sort();
+            to_json(&paths, args.pretty)
+        }
+        OutputFormat::Tsv => {
+            let mut tsv = String::new();
+            if args.tsv_header {
+                tsv.push_str("depth\ttype\tname\tpath\n");
             }
-            PathResult::Empty => {}
+            write_tsv_rows(root, "", 1, &mut tsv);
+            Ok(tsv)
+        }
+        OutputFormat::FlatDepth => {
+            let mut lines = String::new();
+            write_depth_lines(root, "", 1, &mut lines);
+            Ok(lines)
         }
+    };
+    Some(serialized)
+}
+
+/// Serializes `value` as JSON, on one line unless `pretty` is set.
+fn to_json(value: &impl serde::Serialize, pretty: bool) -> Result<String, String> {
+    let json = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    json.map_err(|e| e.to_string())
+}
+
+/// Appends a `depth<TAB>type<TAB>name<TAB>path` row for every entry below `node`,
+/// depth-first in tree order. Top-level entries are at depth 1.
+fn write_tsv_rows(node: &TreeNode, prefix: &str, depth: usize, tsv: &mut String) {
+    for (name, child) in node {
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{prefix}/{name}")
+        };
+        let kind = if child.is_leaf { "file" } else { "dir" };
+        let _ = writeln!(
+            tsv,
+            "{depth}\t{kind}\t{}\t{}",
+            escape_tsv(name),
+            escape_tsv(&path)
+        );
+        write_tsv_rows(child, &path, depth + 1, tsv);
     }
+}
 
-    // Apply sorting if requested
-    if !matches!(args.sort, SortOrder::None) {
-        sort_tree(&mut root, args.sort);
+/// Appends a `depth<TAB>path` line for every entry below `node`, depth-first in
+/// tree order. Top-level entries are at depth 1.
+fn write_depth_lines(node: &TreeNode, prefix: &str, depth: usize, lines: &mut String) {
+    for (name, child) in node {
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{prefix}/{name}")
+        };
+        let _ = writeln!(lines, "{depth}\t{}", escape_tsv(&path));
+        write_depth_lines(child, &path, depth + 1, lines);
     }
+}
 
-    // Count files and directories
-    count_tree(&root, &mut stats);
+/// One `--output ndjson` line.
+#[derive(serde::Serialize)]
+struct NdjsonEntry<'a> {
+    depth: usize,
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    path: &'a str,
+}
 
-    let mut trunk = TreeTrunk::default();
-    println!(".");
-    print_tree(&root, &mut trunk, TreeDepth::root().deeper(), &color_scheme);
+/// Writes a JSON object line for every entry below `node`, depth-first in tree
+/// order, as soon as it is reached. Top-level entries are at depth 1.
+fn write_ndjson(
+    node: &TreeNode,
+    prefix: &str,
+    depth: usize,
+    printer: &mut Printer,
+) -> io::Result<()> {
+    for (name, child) in node {
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{prefix}/{name}")
+        };
+        let entry = NdjsonEntry {
+            depth,
+            name,
+            kind: if child.is_leaf { "file" } else { "dir" },
+            path: &path,
+        };
+        match serde_json::to_string(&entry) {
+            Ok(line) => printer.line(&line)?,
+            Err(e) => eprintln!("Error serializing {path:?}: {e}"),
+        }
+        write_ndjson(child, &path, depth + 1, printer)?;
+    }
+    Ok(())
+}
 
-    if args.stats {
-        println!();
-        println!(
+/// Escapes backslashes, tabs and newlines so a field can't break the TSV layout.
+fn escape_tsv(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+/// Wall-clock time spent in each phase, reported by `--timing`.
+#[derive(Default, Debug)]
+struct Timings {
+    /// Reading and filtering input lines.
+    read: Duration,
+    /// Inserting paths into the tree, then pruning, sorting and counting it.
+    build: Duration,
+    /// Writing the output.
+    render: Duration,
+}
+
+impl Timings {
+    /// Prints one line per phase to stderr.
+    fn report(&self) {
+        eprintln!("Timing: read input {:.3?}", self.read);
+        eprintln!("Timing: build tree {:.3?}", self.build);
+        eprintln!("Timing: render {:.3?}", self.render);
+    }
+}
+
+/// Prints everything that follows the tree: the debug part counts (to stderr),
+/// the `--stats` line and the duplicate-names report.
+fn print_summary(
+    args: &Args,
+    root: &TreeNode,
+    stats: &TreeStats,
+    printer: &mut Printer,
+) -> io::Result<()> {
+    if args.debug_parts {
+        let counts = printer.part_counts();
+        eprintln!(
+            "Tree parts: edge={}, line={}, corner={}, blank={}",
+            counts.edge, counts.line, counts.corner, counts.blank
+        );
+    }
+
+    if let Some(mode) = args.stats {
+        printer.line("")?;
+        printer.line(&format!(
             "Files: {}, Directories: {}, Excluded: {}",
             stats.files, stats.directories, stats.excluded
-        );
+        ))?;
+
+        if mode == StatsMode::IncludeExcluded {
+            printer.line(&format!("Input paths: {}", stats.included + stats.excluded))?;
+            if !stats.excluded_by_extension.is_empty() {
+                printer.line("Excluded by extension:")?;
+                for (extension, count) in &stats.excluded_by_extension {
+                    printer.line(&format!("  {extension}: {count}"))?;
+                }
+            }
+        }
+
+        if args.by_depth {
+            let (_, by_depth) = root.total_and_by_depth_counts();
+            // Depth 0 is the root itself
+            for (depth, count) in by_depth.iter().enumerate().skip(1) {
+                printer.line(&format!("Depth {depth}: {count}"))?;
+            }
+        }
+    }
+
+    if args.duplicate_names {
+        printer.line("")?;
+        print_duplicate_names(root, printer)?;
     }
+
+    if args.legend
+        && let Some(legend) = printer.color_scheme().format_legend(&root.leaf_paths())
+    {
+        printer.line("")?;
+        printer.line(&legend)?;
+    }
+
+    printer.flush()
+}
+
+/// Builds the color scheme from the `[colors]` config and the color-related flags.
+fn build_color_scheme(args: &Args, colors: &config::ColorConfig) -> ColorScheme {
+    let mut color_scheme = if args.no_default_colors {
+        ColorScheme::from_color_config_only(colors)
+    } else {
+        ColorScheme::from_color_config(colors)
+    };
+    color_scheme.set_alternate_folders(args.alt_folders);
+    color_scheme.set_dim_dot(args.dim_dot);
+    color_scheme.set_hash_colors(args.hash_colors);
+    color_scheme.set_guides_color(args.guides_color.as_deref());
+    if let Some(theme) = args.theme {
+        color_scheme.set_theme(theme.into());
+    }
+    color_scheme.set_theme256(args.theme256);
+    if args.age_colors {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let thresholds: Vec<u64> = args
+            .age_buckets
+            .iter()
+            .map(|days| days.saturating_mul(24 * 60 * 60))
+            .collect();
+        color_scheme.set_age_buckets(now, &thresholds);
+    }
+    if let Some(reference) = &args.newer_than {
+        if !matches!(args.input_format, InputFormat::Timestamped) {
+            eprintln!("Warning: --newer-than only applies to --input-format timestamped");
+        }
+        color_scheme.set_newer_than(Some(reference_mtime(reference)));
+    }
+    color_scheme.set_highlight_pattern(args.find.as_deref());
+    color_scheme
+}
+
+/// Returns the modification time of the `--newer-than` reference file in
+/// seconds since the Unix epoch, exiting if it can't be read.
+fn reference_mtime(path: &Path) -> u64 {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+    match modified {
+        Ok(modified) => modified
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+        Err(e) => {
+            eprintln!(
+                "Error: cannot read --newer-than file {}: {e}",
+                path.display()
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Layers command-line overrides on top of the loaded configuration, recording
+/// each overridden category in `sources`.
+fn apply_overrides(config: &mut config::Config, sources: &mut config::ConfigSources, args: &Args) {
+    let cli = config::ConfigSource::Cli;
+    if !args.exclude.is_empty() {
+        config
+            .excluded_files
+            .files
+            .extend(args.exclude.iter().cloned());
+        sources.set("excluded-files", cli);
+    }
+    if !args.include.is_empty() {
+        config
+            .included_files
+            .files
+            .extend(args.include.iter().cloned());
+        sources.set("included-files", cli);
+    }
+
+    if args.no_strip {
+        config.input.strip_pwd = false;
+        sources.set("input.strip-pwd", cli);
+    }
+
+    if args.output.is_some() {
+        sources.set("output.format", cli);
+    }
+
+    if !args.color_ext.is_empty() {
+        config
+            .colors
+            .extensions
+            .extend(args.color_ext.iter().cloned());
+        sources.set("colors.extensions", cli);
+    }
+
+    if let Some((enabled, source)) = color_override(args, ColorEnv::from_env()) {
+        config.colors.enabled = enabled;
+        sources.set("colors.enabled", source);
+    }
+}
+
+/// The color-related environment variables.
+#[derive(Debug, Clone, Copy, Default)]
+struct ColorEnv {
+    /// `NO_COLOR` is set, to any value.
+    no_color: bool,
+    /// `CLICOLOR_FORCE` is set to anything but `0`.
+    force: bool,
+    /// Standard output is a terminal rather than a file or pipe.
+    tty: bool,
+}
+
+impl ColorEnv {
+    fn from_env() -> Self {
+        Self {
+            no_color: env::var_os("NO_COLOR").is_some(),
+            force: env::var_os("CLICOLOR_FORCE").is_some_and(|value| value != "0"),
+            tty: io::stdout().is_terminal(),
+        }
+    }
+}
+
+/// Decides whether colors are enabled when something overrides the config, in
+/// priority order: `--no-color`/`--color`, `CLICOLOR_FORCE`, `NO_COLOR`, then
+/// whether stdout is a terminal, so redirected output has no escape codes.
+/// Returns `None` when the config decides.
+fn color_override(args: &Args, env: ColorEnv) -> Option<(bool, config::ConfigSource)> {
+    let color = if args.no_color {
+        ColorChoice::Never
+    } else {
+        args.color
+    };
+    if color != ColorChoice::Auto {
+        return Some((color == ColorChoice::Always, config::ConfigSource::Cli));
+    }
+    if env.force {
+        return Some((true, config::ConfigSource::Env));
+    }
+    if env.no_color || !env.tty {
+        return Some((false, config::ConfigSource::Env));
+    }
+    None
+}
+
+/// Returns the prefix stripped from input paths: the current directory, or
+/// nothing when `[input] strip-pwd` is off.
+fn pwd_prefix(config: &config::Config) -> String {
+    if !config.input.strip_pwd {
+        return String::new();
+    }
+    let current_dir = env::current_dir().expect("Failed to get current directory");
+    current_dir
+        .to_str()
+        .expect("Failed to convert PathBuf to string")
+        .to_owned()
+}
+
+/// Prints the winning source of each config category for `--show-sources`.
+fn print_sources(sources: &config::ConfigSources) {
+    let sources: Vec<String> = sources
+        .iter()
+        .map(|(category, source)| format!("{category}={source}"))
+        .collect();
+    eprintln!("Config sources: {}", sources.join(" "));
+}
+
+/// Picks the output format: `--output` if given, else `[output] format` from the
+/// config, else tree. An unknown format in the config is warned about and ignored.
+fn resolve_output_format(cli: Option<OutputFormat>, config: &config::OutputConfig) -> OutputFormat {
+    cli.or_else(|| {
+        let format = config.format.as_deref()?;
+        let parsed = OutputFormat::from_str(format, true);
+        if parsed.is_err() {
+            eprintln!("Warning: ignoring unknown [output] format {format:?} in the config");
+        }
+        parsed.ok()
+    })
+    .unwrap_or(OutputFormat::Tree)
+}
+
+/// Parses a `--field` value: a nonzero field number.
+fn parse_field(value: &str) -> Result<i32, String> {
+    match value.parse::<i32>() {
+        Ok(0) => Err("fields are numbered from 1 (or from -1 at the end)".to_string()),
+        Ok(field) => Ok(field),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parses a `--color-ext` value such as `.rs=red`, `rs=#ff8800` or `md=cyan`.
+///
+/// A missing leading dot is added to the extension. The color must be a color
+/// name, a `#rgb`/`#rrggbb` hex color, or a raw ANSI escape code.
+fn parse_color_ext(value: &str) -> Result<(String, String), String> {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+
+    let Some((ext, color)) = value.split_once('=') else {
+        return Err(format!("expected EXT=COLOR, got {value:?}"));
+    };
+    let ext = ext.trim_start_matches('.');
+    if ext.is_empty() {
+        return Err(format!("missing extension in {value:?}"));
+    }
+
+    let is_hex =
+        |hex: &str| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    let valid_color = color.strip_prefix('#').map_or_else(
+        || NAMES.contains(&color.to_lowercase().as_str()) || color.starts_with('\x1b'),
+        is_hex,
+    );
+    if !valid_color {
+        return Err(format!("invalid color {color:?} in {value:?}"));
+    }
+
+    Ok((format!(".{ext}"), color.to_string()))
+}
+
+/// Prints the resolved configuration and effective settings to stderr.
+fn print_diagnostics(
+    args: &Args,
+    config: &config::Config,
+    status: &config::ConfigStatus,
+    color_enabled: bool,
+) {
+    let sort = args
+        .sort
+        .to_possible_value()
+        .map_or_else(String::new, |value| value.get_name().to_string());
+
+    eprintln!("Config path: {}", config::Config::config_path().display());
+    eprintln!("Config status: {status}");
+    eprintln!("Sort: {sort}");
+    eprintln!(
+        "Color: {}",
+        if color_enabled { "enabled" } else { "disabled" }
+    );
+    eprintln!(
+        "Patterns: {} excluded, {} included",
+        config.excluded_files.files.len(),
+        config.included_files.files.len()
+    );
 }
 
 /// Handles subcommands.
 fn handle_command(command: &Command) {
     match command {
-        Command::Config { default, init } => {
+        Command::Config {
+            default,
+            init,
+            force,
+        } => {
             if *init {
-                initialize_config();
+                initialize_config(*force);
             } else if *default {
                 print_default_config();
             } else {
                 show_config_info();
             }
         }
+        Command::Examples => print!("{EXAMPLES}"),
     }
 }
 
 /// Shows information about the current configuration.
 fn show_config_info() {
+    let (_, status) = config::Config::load();
+    if status == config::ConfigStatus::Embedded
+        && let Some(table) = config::Config::chezmoi_data_files()
+    {
+        let chezmoi_path = config::Config::chezmoi_config_path();
+        println!("Configuration file: {}", chezmoi_path.display());
+        println!("\nCurrent configuration (the [data.files] table):");
+        println!("{}", toml::to_string(&table).unwrap_or_default());
+        return;
+    }
+
     let config_path = config::Config::config_path();
     println!("Configuration file: {}", config_path.display());
 
@@ -189,61 +1227,494 @@ fn print_default_config() {
     println!("{}", config::Config::default_config_toml());
 }
 
-/// Initializes the configuration file with default values.
-fn initialize_config() {
+/// Initializes the configuration file from the commented default template.
+///
+/// An existing file is left untouched and the process exits with an error,
+/// unless `force` is set; then the old file is first copied to `*.bak`.
+fn initialize_config(force: bool) {
     let config_path = config::Config::config_path();
 
     if config_path.exists() {
-        eprintln!(
-            "Configuration file already exists at: {}",
-            config_path.display()
+        if !force {
+            eprintln!(
+                "Configuration file already exists at: {}",
+                config_path.display()
+            );
+            eprintln!("Use --force to replace it (the old file is kept as a .bak backup).");
+            process::exit(1);
+        }
+
+        let mut backup_name = config_path.file_name().unwrap_or_default().to_os_string();
+        backup_name.push(".bak");
+        let backup_path = config_path.with_file_name(backup_name);
+        if let Err(e) = std::fs::copy(&config_path, &backup_path) {
+            eprintln!("Error backing up configuration file: {e}");
+            process::exit(1);
+        }
+        println!(
+            "Existing configuration backed up to: {}",
+            backup_path.display()
         );
-        eprintln!("Remove it first or edit it manually.");
-        return;
     }
 
     if let Some(parent) = config_path.parent()
         && let Err(e) = std::fs::create_dir_all(parent)
     {
         eprintln!("Error creating config directory: {e}");
-        return;
+        process::exit(1);
     }
 
-    let default_config = config::Config::default_config_toml();
-    if let Err(e) = std::fs::write(&config_path, default_config) {
+    if let Err(e) = config::write_atomic(&config_path, &config::Config::default_config_toml()) {
         eprintln!("Error writing configuration file: {e}");
-        return;
+        process::exit(1);
+    }
+
+    println!("Configuration file created at: {}", config_path.display());
+}
+
+/// Result of processing a path.
+enum PathResult {
+    /// Path should be included in the tree.
+    Included(String),
+    /// Path was excluded by filters.
+    Excluded,
+    /// Path was empty or invalid.
+    Empty,
+    /// Path names the root itself: `.` or exactly the current directory.
+    Root,
+}
+
+/// Processes a path by filtering and normalizing it.
+fn process_path(path: &str, current_dir: &str, config: &config::Config) -> PathResult {
+    let trimmed_path = path.trim_end_matches('/');
+    // `./src/main.rs` and `src/main.rs` name the same file
+    let trimmed_path = trimmed_path.strip_prefix("./").unwrap_or(trimmed_path);
+
+    if trimmed_path.is_empty() {
+        return PathResult::Empty;
+    }
+
+    // The root is already drawn as `.`, so a line naming it adds no entry
+    if trimmed_path == "." || trimmed_path == current_dir {
+        return PathResult::Root;
+    }
+
+    if should_exclude(trimmed_path, config) {
+        return PathResult::Excluded;
+    }
+
+    let relative_path = trimmed_path
+        .strip_prefix(current_dir)
+        .unwrap_or(trimmed_path);
+    PathResult::Included(relative_path.trim_start_matches('/').to_owned())
+}
+
+/// Reads paths one per line from stdin (or `chezmoi managed`) and adds the ones
+/// that pass filtering to `root`.
+///
+/// With `stream_to`, completed top-level entries are rendered while reading.
+/// Streaming stops with a warning if an entry that was already rendered comes
+/// back, and the rest of the input is rendered once it ends.
+/// Returns the root label taken from the first line with `--first-line-root`.
+fn read_line_input(
+    args: &Args,
+    config: &config::Config,
+    current_dir: &str,
+    root: &mut TreeNode,
+    stats: &mut TreeStats,
+    mut stream_to: Option<&mut Printer>,
+    timings: &mut Timings,
+) -> Option<String> {
+    let input: Box<dyn BufRead> = match &args.from_chezmoi_source {
+        Some(source) => Box::new(io::Cursor::new(chezmoi_managed(source))),
+        None => Box::new(io::stdin().lock()),
+    };
+    let input = decompress_gzip(input, args.gzip);
+    let mut lines = BoundedLines::new(input, args.max_line_bytes);
+
+    // With --first-line-root, the first line names the root and replaces the
+    // current directory as the prefix stripped from the remaining paths
+    let root_label = if args.first_line_root {
+        lines
+            .next()
+            .and_then(|line| input_path(line, args))
+            .map(|line| match line.trim_end_matches('/') {
+                "" => line,
+                trimmed => trimmed.to_string(),
+            })
+    } else {
+        None
+    };
+    let strip_prefix = root_label.as_deref().unwrap_or(current_dir);
+    let timestamped = matches!(args.input_format, InputFormat::Timestamped);
+    let mut flushed = HashSet::new();
+
+    for line in lines {
+        let mut mtime = None;
+        let line = line.map(|line| match line {
+            InputLine::Path(text) if timestamped || args.field.is_some() => {
+                let (time, path) = if timestamped {
+                    split_mtime(&text)
+                } else {
+                    (None, text.as_str())
+                };
+                mtime = time;
+                let path = args
+                    .field
+                    .map_or(Some(path), |field| select_field(path, field));
+                InputLine::Path(path.unwrap_or_default().to_string())
+            }
+            other => other,
+        });
+        let Some(path) = input_path(line, args) else {
+            continue;
+        };
+
+        match process_path(&path, strip_prefix, config) {
+            PathResult::Included(relative_path) => {
+                stats.included += 1;
+                let mut parts: Vec<&str> =
+                    relative_path.split('/').filter(|p| !p.is_empty()).collect();
+                let absolute_top;
+                if args.multi_root
+                    && path.starts_with('/')
+                    && (strip_prefix.is_empty() || !path.starts_with(strip_prefix))
+                    && let Some(first) = parts.first_mut()
+                {
+                    absolute_top = format!("/{first}");
+                    *first = &absolute_top;
+                }
+                if let Some(printer) = stream_to.as_deref_mut()
+                    && let Some(first) = parts.first()
+                {
+                    if flushed.contains(*first) {
+                        eprintln!(
+                            "Warning: {first:?} reappeared after it was printed; --stream needs input grouped by top-level entry, streaming stopped"
+                        );
+                        stream_to = None;
+                    } else {
+                        exit_on_write_error(flush_completed(
+                            root,
+                            first,
+                            printer,
+                            stats,
+                            &mut flushed,
+                        ));
+                    }
+                }
+                let insert_started = Instant::now();
+                if args.no_trim_trailing_slash && path.ends_with('/') {
+                    root.add_dir(parts);
+                } else {
+                    root.add_path(parts).mtime = mtime;
+                }
+                timings.build += insert_started.elapsed();
+            }
+            PathResult::Excluded => {
+                stats.record_excluded(&path);
+            }
+            PathResult::Root => stats.included += 1,
+            PathResult::Empty => {}
+        }
+    }
+
+    root_label
+}
+
+/// The first two bytes of every gzip stream.
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+
+/// Decompresses `input` when it is gzip data: when `force` is set, or when it
+/// starts with the gzip magic bytes. Other input is returned unchanged.
+fn decompress_gzip(mut input: Box<dyn BufRead>, force: bool) -> Box<dyn BufRead> {
+    let is_gzip = force
+        || input
+            .fill_buf()
+            .is_ok_and(|buffer| buffer.starts_with(GZIP_MAGIC));
+    if is_gzip {
+        Box::new(io::BufReader::new(flate2::read::MultiGzDecoder::new(input)))
+    } else {
+        input
+    }
+}
+
+/// Splits the `MTIME<TAB>` prefix off a line of `--input-format timestamped`.
+///
+/// MTIME is in seconds since the Unix epoch; a fractional part, as printed by
+/// `find -printf '%T@'`, is ignored. A line without a valid prefix is taken
+/// whole as a path with no timestamp.
+fn split_mtime(line: &str) -> (Option<u64>, &str) {
+    let Some((time, path)) = line.split_once('\t') else {
+        return (None, line);
+    };
+    let seconds = time.split_once('.').map_or(time, |(seconds, _)| seconds);
+    seconds
+        .parse()
+        .map_or((None, line), |mtime| (Some(mtime), path))
+}
+
+/// Returns the `field`th whitespace-separated field of `line` for `--field`:
+/// 1-based from the start, or from the end when negative. `None` if the line
+/// has too few fields.
+fn select_field(line: &str, field: i32) -> Option<&str> {
+    let index = usize::try_from(field.unsigned_abs()).ok()?.checked_sub(1)?;
+    if field > 0 {
+        line.split_whitespace().nth(index)
+    } else {
+        line.split_whitespace().rev().nth(index)
+    }
+}
+
+/// Runs `chezmoi managed` for the `source` directory and returns its output.
+///
+/// Exits with an error if `chezmoi` is not on `PATH` or the command fails.
+fn chezmoi_managed(source: &Path) -> Vec<u8> {
+    let output = process::Command::new("chezmoi")
+        .args(["managed", "--path-style", "absolute", "--source"])
+        .arg(source)
+        .stderr(process::Stdio::inherit())
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => output.stdout,
+        Ok(output) => {
+            eprintln!("Error: `chezmoi managed` failed ({})", output.status);
+            process::exit(1);
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            eprintln!(
+                "Error: `chezmoi` was not found on PATH. Install it from \
+                 https://www.chezmoi.io/install/ or pipe paths into chezmoi-files instead."
+            );
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error: failed to run `chezmoi`: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Reads a tree written by `--output json` from stdin and adds its entries to
+/// `root`, filtering each path like a line of input.
+///
+/// Exits with an error if the input is not valid JSON of that shape.
+fn read_json_input(
+    root: &mut TreeNode,
+    current_dir: &str,
+    config: &config::Config,
+    stats: &mut TreeStats,
+    timings: &mut Timings,
+) {
+    let input: TreeNode = match serde_json::from_reader(io::stdin().lock()) {
+        Ok(tree) => tree,
+        Err(e) => {
+            eprintln!("Error: invalid JSON input: {e}");
+            process::exit(1);
+        }
+    };
+
+    let insert_started = Instant::now();
+    insert_json_entries(&input, "", root, current_dir, config, stats);
+    timings.build += insert_started.elapsed();
+}
+
+/// Adds the files and empty directories below `node` to `root`, in tree order.
+///
+/// Empty directories are added with [`TreeNode::add_dir`] so they stay
+/// directories, keeping `--input-format json --output json` a round trip.
+fn insert_json_entries(
+    node: &TreeNode,
+    prefix: &str,
+    root: &mut TreeNode,
+    current_dir: &str,
+    config: &config::Config,
+    stats: &mut TreeStats,
+) {
+    for (name, child) in &node.children {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}/{name}")
+        };
+
+        if !child.is_leaf && !child.children.is_empty() {
+            insert_json_entries(child, &path, root, current_dir, config, stats);
+            continue;
+        }
+
+        match process_path(&path, current_dir, config) {
+            PathResult::Included(relative_path) => {
+                stats.included += 1;
+                let parts = relative_path.split('/').filter(|p| !p.is_empty());
+                if child.is_leaf {
+                    root.add_path(parts);
+                } else {
+                    root.add_dir(parts);
+                }
+            }
+            PathResult::Excluded => stats.record_excluded(&path),
+            PathResult::Root => stats.included += 1,
+            PathResult::Empty => {}
+        }
+    }
+}
+
+/// A line read by [`BoundedLines`].
+#[derive(Debug, PartialEq, Eq)]
+enum InputLine {
+    /// A complete line, without its line ending.
+    Path(String),
+    /// A line longer than the limit; holds its length in bytes. Its contents are discarded.
+    TooLong(usize),
+}
+
+/// The UTF-8 encoding of U+FEFF, the byte order mark.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Iterates over the lines of a reader, buffering at most `max_bytes` of any line.
+///
+/// Unlike [`BufRead::lines`], an overlong line (e.g. binary input) is consumed and
+/// reported as [`InputLine::TooLong`] instead of being held in memory in full.
+/// A leading byte order mark is dropped from every line.
+struct BoundedLines<R> {
+    reader: R,
+    max_bytes: usize,
+}
+
+impl<R: BufRead> BoundedLines<R> {
+    const fn new(reader: R, max_bytes: usize) -> Self {
+        Self { reader, max_bytes }
     }
 
-    println!("Configuration file created at: {}", config_path.display());
+    /// Reads one line into `line`, keeping at most `max_bytes` of it.
+    ///
+    /// Returns the full length of the line, or `None` at end of input.
+    fn read_line(&mut self, line: &mut Vec<u8>) -> io::Result<Option<usize>> {
+        let mut len = 0;
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if available.is_empty() {
+                return Ok((len > 0).then_some(len));
+            }
+
+            let newline = available.iter().position(|&b| b == b'\n');
+            let done = newline.is_some();
+            let chunk = &available[..newline.unwrap_or(available.len())];
+            let room = self.max_bytes.saturating_add(1).saturating_sub(line.len());
+            line.extend_from_slice(&chunk[..chunk.len().min(room)]);
+            len += chunk.len();
+
+            let consumed = chunk.len() + usize::from(done);
+            self.reader.consume(consumed);
+            if done {
+                return Ok(Some(len));
+            }
+        }
+    }
 }
 
-/// Result of processing a path.
-enum PathResult {
-    /// Path should be included in the tree.
-    Included(String),
-    /// Path was excluded by filters.
-    Excluded,
-    /// Path was empty or invalid.
-    Empty,
+impl<R: BufRead> Iterator for BoundedLines<R> {
+    type Item = io::Result<InputLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = Vec::new();
+        let mut len = match self.read_line(&mut line) {
+            Ok(Some(len)) => len,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+
+        // Only a CR that really ends the line belongs to a CRLF; in a
+        // truncated line it could be any byte in the middle
+        if line.len() == len && line.last() == Some(&b'\r') {
+            line.pop();
+            len -= 1;
+        }
+        // Windows tools may start a file (or each line) with a UTF-8 BOM, which
+        // is not part of the path
+        if line.starts_with(UTF8_BOM) {
+            line.drain(..UTF8_BOM.len());
+            len -= UTF8_BOM.len();
+        }
+        if len > self.max_bytes {
+            return Some(Ok(InputLine::TooLong(len)));
+        }
+
+        Some(
+            String::from_utf8(line)
+                .map(InputLine::Path)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        )
+    }
 }
 
-/// Processes a path by filtering and normalizing it.
-fn process_path(path: &str, current_dir: &str, config: &config::Config) -> PathResult {
-    let trimmed_path = path.trim_end_matches('/');
+/// Turns a line of input into a path to process, applying `--normalize`.
+///
+/// Read errors, overlong lines and paths escaping the root are reported on
+/// stderr and yield `None`.
+fn input_path(line: io::Result<InputLine>, args: &Args) -> Option<String> {
+    let path = match line {
+        Ok(InputLine::Path(path)) => path,
+        Ok(InputLine::TooLong(len)) => {
+            eprintln!(
+                "Warning: skipping input line of {len} bytes (limit {})",
+                args.max_line_bytes
+            );
+            return None;
+        }
+        Err(e) => {
+            eprintln!("Error reading line: {e}");
+            return None;
+        }
+    };
 
-    if trimmed_path.is_empty() {
-        return PathResult::Empty;
+    let path = match args.tabs {
+        TabMode::Keep => path,
+        TabMode::Strip => path.replace('\t', ""),
+    };
+
+    if !args.normalize {
+        return Some(path);
     }
 
-    if should_exclude(trimmed_path, config) {
-        return PathResult::Excluded;
+    let normalized = normalize_path(&path);
+    if normalized.is_none() {
+        eprintln!("Warning: skipping path that escapes the root: {path}");
     }
+    normalized
+}
 
-    let relative_path = trimmed_path
-        .strip_prefix(current_dir)
-        .unwrap_or(trimmed_path);
-    PathResult::Included(relative_path.trim_start_matches('/').to_owned())
+/// Logically resolves `.` and `..` components in `path`, without touching the filesystem.
+///
+/// `a/./b/../c` becomes `a/c`. Returns `None` when a relative path pops above its
+/// starting point (`a/../../b`); for absolute paths, `..` at `/` stays at `/`.
+fn normalize_path(path: &str) -> Option<String> {
+    let is_absolute = path.starts_with('/');
+    let mut components: Vec<&str> = Vec::new();
+
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                if components.pop().is_none() && !is_absolute {
+                    return None;
+                }
+            }
+            _ => components.push(component),
+        }
+    }
+
+    let joined = components.join("/");
+    Some(if is_absolute {
+        format!("/{joined}")
+    } else {
+        joined
+    })
 }
 
 /// Determines if a path should be excluded based on configuration.
@@ -256,8 +1727,74 @@ fn should_exclude(path: &str, config: &config::Config) -> bool {
     is_excluded && !is_included
 }
 
+/// A compiled `[[sort-rules]]` entry: directories matching `pattern` use `order`.
+struct SortOverride {
+    pattern: glob::Pattern,
+    order: SortOrder,
+}
+
+/// Compiles the configured sort rules, warning about and skipping invalid ones.
+fn compile_sort_rules(rules: &[config::SortRule]) -> Vec<SortOverride> {
+    rules
+        .iter()
+        .filter_map(|rule| {
+            let Ok(order) = SortOrder::from_str(&rule.mode, true) else {
+                eprintln!(
+                    "Warning: ignoring sort rule for {:?}: unknown mode {:?}",
+                    rule.path, rule.mode
+                );
+                return None;
+            };
+            match glob::Pattern::new(&rule.path) {
+                Ok(pattern) => Some(SortOverride { pattern, order }),
+                Err(e) => {
+                    eprintln!(
+                        "Warning: ignoring invalid sort rule path {:?}: {e}",
+                        rule.path
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
 /// Sorts the tree recursively based on the specified sort order.
-fn sort_tree(node: &mut TreeNode, sort_order: SortOrder) {
+///
+/// A directory whose path matches one of `overrides` sorts its children with
+/// the first matching rule's order instead; other directories use `sort_order`.
+fn sort_tree(node: &mut TreeNode, sort_order: SortOrder, overrides: &[SortOverride]) {
+    sort_subtree(node, "", sort_order, overrides);
+}
+
+fn sort_subtree(
+    node: &mut TreeNode,
+    path: &str,
+    sort_order: SortOrder,
+    overrides: &[SortOverride],
+) {
+    let order = overrides
+        .iter()
+        .find(|rule| !path.is_empty() && rule.pattern.matches(path))
+        .map_or(sort_order, |rule| rule.order);
+    sort_children(node, order);
+
+    // Recursively sort children
+    for (name, child) in &mut node.children {
+        if child.is_leaf {
+            continue;
+        }
+        let child_path = if path.is_empty() {
+            name.clone()
+        } else {
+            format!("{path}/{name}")
+        };
+        sort_subtree(child, &child_path, sort_order, overrides);
+    }
+}
+
+/// Sorts the direct children of `node`.
+fn sort_children(node: &mut TreeNode, sort_order: SortOrder) {
     match sort_order {
         SortOrder::None => {}
         SortOrder::Name => {
@@ -281,69 +1818,213 @@ fn sort_tree(node: &mut TreeNode, sort_order: SortOrder) {
                 }
             });
         }
+        SortOrder::TypeName => {
+            node.children
+                .sort_by(|k1, v1, k2, v2| type_name_key(k1, v1).cmp(&type_name_key(k2, v2)));
+        }
+        SortOrder::FilesFirst => {
+            node.children
+                .sort_by(|k1, v1, k2, v2| (!v1.is_leaf, k1).cmp(&(!v2.is_leaf, k2)));
+        }
     }
+}
+
+/// Moves files after all subdirectories in every directory of the tree.
+///
+/// The partition is stable, so it keeps the relative order produced by any
+/// previous sort within the directories and within the files.
+fn move_files_last(node: &mut TreeNode) {
+    node.children
+        .sort_by(|_, v1, _, v2| v1.is_leaf.cmp(&v2.is_leaf));
 
-    // Recursively sort children
     for (_, child) in &mut node.children {
-        sort_tree(child, sort_order);
+        move_files_last(child);
     }
 }
 
+/// Removes every file from the tree for `--dirs-only`. Directories stay, even
+/// those left without children.
+fn remove_files(node: &mut TreeNode) {
+    node.children.retain(|_, child| !child.is_leaf);
+    for (_, child) in &mut node.children {
+        remove_files(child);
+    }
+}
+
+/// Sort key for [`SortOrder::TypeName`].
+///
+/// The key has three levels, compared in order:
+/// 1. kind: directories before files;
+/// 2. extension: files only, the text after the last `.` (not counting a
+///    leading dot); extensionless files sort before any extension;
+/// 3. name: the full entry name.
+fn type_name_key<'a>(name: &'a str, node: &TreeNode) -> (bool, Option<&'a str>, &'a str) {
+    let extension = if node.is_leaf {
+        name.get(1..)
+            .and_then(|rest| rest.rsplit_once('.'))
+            .map(|(_, ext)| ext)
+    } else {
+        None
+    };
+    (node.is_leaf, extension, name)
+}
+
 /// Counts files and directories in the tree.
 fn count_tree(node: &TreeNode, stats: &mut TreeStats) {
-    for (_, child) in &node.children {
-        if child.is_leaf {
-            stats.files += 1;
-        } else {
-            stats.directories += 1;
-            count_tree(child, stats);
+    let counts = node.count();
+    stats.files += counts.files;
+    stats.directories += counts.directories;
+}
+
+/// Warns on stderr about each directory under `node` (itself included) that has
+/// more than `limit` direct children. `path` is the directory's path, empty for
+/// the root.
+fn warn_wide_directories(node: &TreeNode, path: &str, limit: usize) {
+    if node.children.len() > limit {
+        eprintln!(
+            "Warning: {} has {} direct children (more than {limit})",
+            if path.is_empty() { "." } else { path },
+            node.children.len()
+        );
+    }
+
+    for (name, child) in node {
+        if !child.is_leaf {
+            let child_path = if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{path}/{name}")
+            };
+            warn_wide_directories(child, &child_path, limit);
         }
     }
 }
-/// Prints a tree structure.
-///
-/// This function prints a tree structure with the specified root node, trunk, depth,
-/// and color scheme using a depth-first traversal.
-///
-/// # Arguments
-///
-/// * `node` - A reference to the `TreeNode` that is currently being processed.
-/// * `trunk` - A mutable reference to the `TreeTrunk` that is used to store the tree structure.
-/// * `depth` - The current depth of the tree.
-/// * `color_scheme` - A reference to the `ColorScheme` that is used to colorize the output.
-///
-/// # Example
-///
-/// ```no_run
-/// use chezmoi_files::{TreeNode, TreeTrunk, TreeDepth, ColorScheme};
+
+/// Groups leaf paths by file name, keeping only names that appear more than once.
 ///
-/// let node = TreeNode::new();
-/// let mut trunk = TreeTrunk::default();
-/// let depth = TreeDepth::root().deeper();
-/// let color_scheme = ColorScheme::new();
-/// print_tree(&node, &mut trunk, depth, &color_scheme);
-/// ```
-fn print_tree(
-    node: &TreeNode,
-    trunk: &mut TreeTrunk,
-    depth: TreeDepth,
-    color_scheme: &ColorScheme,
-) {
-    let children = &node.children;
-    let last_key = children.keys().last();
+/// Names are returned in alphabetical order; paths keep their tree order.
+fn find_duplicate_names(node: &TreeNode) -> Vec<(String, Vec<String>)> {
+    let mut by_name: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for path in node.leaf_paths() {
+        let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+        by_name.entry(name).or_default().push(path);
+    }
+
+    by_name
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect()
+}
+
+/// Prints the path of every file for `--files-only`, colored like the file's
+/// entry in the tree would be.
+fn print_file_paths(root: &TreeNode, printer: &mut Printer) -> io::Result<()> {
+    let paths = root.leaf_paths();
+    for path in &paths {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let color_scheme = printer.color_scheme();
+        let line =
+            color_scheme.format_at_depth("", "", path, false, 0, color_scheme.file_color(name));
+        printer.line(&line)?;
+    }
+    printer.truncation_notice(paths.len())
+}
+
+/// Prints the duplicate-names report.
+fn print_duplicate_names(node: &TreeNode, printer: &mut Printer) -> io::Result<()> {
+    let duplicates = find_duplicate_names(node);
+
+    if duplicates.is_empty() {
+        return printer.line("No duplicate names found.");
+    }
+
+    printer.line("Duplicate names:")?;
+    for (name, paths) in duplicates {
+        printer.line(&format!("  {name}"))?;
+        for path in paths {
+            printer.line(&format!("    {path}"))?;
+        }
+    }
+    Ok(())
+}
 
-    for (name, subtree) in children {
-        let is_last = Some(name) == last_key;
-        let params = TreeParams::new(depth, is_last);
-        let parts = trunk.new_row(params);
+/// Renders and removes the pending top-level entry once input has moved on to another one.
+///
+/// Used by `--stream`: input that arrives grouped by top-level entry can be rendered
+/// incrementally. Flushed entries are always drawn as non-last, and their counts are
+/// added to `stats` since they no longer live in the tree. Their names are added to
+/// `flushed`, so the caller can tell when one reappears later in the input.
+fn flush_completed(
+    root: &mut TreeNode,
+    next: &str,
+    printer: &mut Printer,
+    stats: &mut TreeStats,
+    flushed: &mut HashSet<String>,
+) -> io::Result<()> {
+    if root.children.is_empty() || root.children.contains_key(next) {
+        return Ok(());
+    }
 
-        let prefix: String = parts.iter().map(|part| part.ascii_art()).collect();
-        color_scheme.print_with_color(&prefix, name);
+    for (name, subtree) in root.children.drain(..) {
+        printer.write_entry(&name, &subtree, false, TreeDepth::root().deeper())?;
+        flushed.insert(name);
+        printer.write_group_separator(TreeDepth::root().deeper())?;
 
-        if !subtree.is_leaf {
-            print_tree(subtree, trunk, depth.deeper(), color_scheme);
+        if subtree.is_leaf {
+            stats.files += 1;
+        } else {
+            stats.directories += 1;
+            count_tree(&subtree, stats);
         }
     }
+    printer.flush()
+}
+
+/// Builds the render options selected on the command line.
+fn render_options(args: &Args) -> RenderOptions {
+    RenderOptions {
+        separator: args.separator.clone(),
+        collapse: args.collapse,
+        max_label_width: args.max_label_width.map(usize::from),
+        max_depth: args.max_depth.map(usize::from),
+        ascii: args.ascii_safe_fallback
+            && !locale_supports_utf8(
+                env::var("LC_ALL").ok().as_deref(),
+                env::var("LC_CTYPE").ok().as_deref(),
+                env::var("LANG").ok().as_deref(),
+            ),
+        prefix_only: args.print_prefix_only,
+        group_separator: args.group_separator,
+        dim_empty_dirs: args.dim_empty_dirs,
+        null_root: args.null_root,
+        color_by_top_dir: args.color_by_top_dir,
+        compact_colors: args.compact_colors,
+        strip_extensions: args.strip_extensions,
+        line_prefix: args.line_prefix.clone(),
+        trailing_newline: !args.no_trailing_newline,
+        max_lines: args.max_total_lines,
+        width: args.render_width.map(usize::from),
+    }
+}
+
+/// Decides whether the output encoding can represent box drawing characters.
+///
+/// Follows the POSIX precedence of `LC_ALL`, then `LC_CTYPE`, then `LANG`; the
+/// first non-empty value wins. With no locale set at all, UTF-8 is assumed.
+/// The Windows console code page can't be queried without FFI (the crate
+/// forbids `unsafe`), so Windows users rely on the same variables.
+fn locale_supports_utf8(lc_all: Option<&str>, lc_ctype: Option<&str>, lang: Option<&str>) -> bool {
+    let Some(locale) = [lc_all, lc_ctype, lang]
+        .into_iter()
+        .flatten()
+        .find(|value| !value.is_empty())
+    else {
+        return true;
+    };
+
+    let locale = locale.to_ascii_lowercase();
+    locale.contains("utf-8") || locale.contains("utf8")
 }
 
 #[cfg(test)]
@@ -379,6 +2060,23 @@ mod tests {
         assert_eq!(stats.excluded, 0);
     }
 
+    #[test]
+    fn test_tree_stats_record_excluded() {
+        let mut stats = TreeStats::default();
+        stats.record_excluded("notes/todo.bak");
+        stats.record_excluded("old.bak");
+        stats.record_excluded("path/DS_Store");
+        stats.record_excluded(".zsh_history");
+
+        assert_eq!(stats.excluded, 4);
+        let breakdown: Vec<_> = stats
+            .excluded_by_extension
+            .iter()
+            .map(|(ext, count)| (ext.as_str(), *count))
+            .collect();
+        assert_eq!(breakdown, vec![("(none)", 2), (".bak", 2)]);
+    }
+
     #[test]
     fn test_process_path_empty() {
         let config = config::Config::default();
@@ -413,6 +2111,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_process_path_root() {
+        let config = config::Config::default();
+        for path in ["/current/dir", "/current/dir/", ".", "./"] {
+            let result = process_path(path, "/current/dir", &config);
+            assert!(matches!(result, PathResult::Root), "{path:?}");
+        }
+    }
+
+    #[test]
+    fn test_process_path_strip_leading_dot_slash() {
+        let config = config::Config::default();
+        let result = process_path("./src/main.rs", "/current/dir", &config);
+        match result {
+            PathResult::Included(path) => assert_eq!(path, "src/main.rs"),
+            _ => panic!("Expected Included variant"),
+        }
+    }
+
     #[test]
     fn test_should_exclude_default() {
         let config = config::Config::default();
@@ -443,7 +2160,7 @@ mod tests {
         root.add_path(vec!["a.txt"]);
         root.add_path(vec!["b.txt"]);
 
-        sort_tree(&mut root, SortOrder::None);
+        sort_tree(&mut root, SortOrder::None, &[]);
 
         let keys: Vec<_> = root.children.keys().collect();
         // Order should remain as inserted
@@ -457,7 +2174,7 @@ mod tests {
         root.add_path(vec!["a.txt"]);
         root.add_path(vec!["b.txt"]);
 
-        sort_tree(&mut root, SortOrder::Name);
+        sort_tree(&mut root, SortOrder::Name, &[]);
 
         let keys: Vec<_> = root.children.keys().collect();
         assert_eq!(keys, vec!["a.txt", "b.txt", "c.txt"]);
@@ -470,7 +2187,7 @@ mod tests {
         root.add_path(vec!["dir", "nested.txt"]);
         root.add_path(vec!["file.rs"]);
 
-        sort_tree(&mut root, SortOrder::Type);
+        sort_tree(&mut root, SortOrder::Type, &[]);
 
         let keys: Vec<_> = root.children.keys().collect();
         // Directory should come before files
@@ -484,12 +2201,307 @@ mod tests {
         root.add_path(vec!["file.rs"]);
         root.add_path(vec!["file.md"]);
 
-        sort_tree(&mut root, SortOrder::Type);
+        sort_tree(&mut root, SortOrder::Type, &[]);
 
         // Should be sorted by extension
         assert_eq!(root.children.keys().count(), 3);
     }
 
+    #[test]
+    fn test_sort_tree_type_name() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["b.txt"]);
+        root.add_path(vec!["zeta", "inner.txt"]);
+        root.add_path(vec!["a.txt"]);
+        root.add_path(vec!["Makefile"]);
+        root.add_path(vec!["main.rs"]);
+        root.add_path(vec![".zshrc"]);
+        root.add_path(vec!["alpha.d", "inner.txt"]);
+        root.add_path(vec!["lib.rs"]);
+
+        sort_tree(&mut root, SortOrder::TypeName, &[]);
+
+        let keys: Vec<_> = root.children.keys().collect();
+        assert_eq!(
+            keys,
+            vec![
+                "alpha.d", "zeta", ".zshrc", "Makefile", "lib.rs", "main.rs", "a.txt", "b.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_tree_files_first() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["zeta", "inner.txt"]);
+        root.add_path(vec!["b.txt"]);
+        root.add_path(vec!["alpha", "inner.txt"]);
+        root.add_path(vec!["a.rs"]);
+
+        sort_tree(&mut root, SortOrder::FilesFirst, &[]);
+
+        let keys: Vec<_> = root.children.keys().collect();
+        assert_eq!(keys, vec!["a.rs", "b.txt", "alpha", "zeta"]);
+    }
+
+    #[test]
+    fn test_select_field() {
+        let line = "-rw-r--r--  1 me  staff  10 Jan  1 12:00 src/main.rs";
+        assert_eq!(select_field(line, -1), Some("src/main.rs"));
+        assert_eq!(select_field(line, 1), Some("-rw-r--r--"));
+        assert_eq!(select_field(line, 3), Some("me"));
+        assert_eq!(select_field(line, -2), Some("12:00"));
+        assert_eq!(select_field(line, 10), None);
+        assert_eq!(select_field(line, -10), None);
+        assert_eq!(select_field("", 1), None);
+
+        assert_eq!(parse_field("-1"), Ok(-1));
+        assert!(parse_field("0").is_err());
+        assert!(parse_field("x").is_err());
+    }
+
+    #[test]
+    fn test_parse_color_ext() {
+        assert_eq!(
+            parse_color_ext(".rs=green"),
+            Ok((".rs".to_string(), "green".to_string()))
+        );
+        assert_eq!(
+            parse_color_ext("md=#ff8800"),
+            Ok((".md".to_string(), "#ff8800".to_string()))
+        );
+        assert!(parse_color_ext(".rs").is_err());
+        assert!(parse_color_ext("=red").is_err());
+        assert!(parse_color_ext(".rs=chartreuse").is_err());
+        assert!(parse_color_ext(".rs=#12").is_err());
+    }
+
+    #[test]
+    fn test_escape_tsv() {
+        assert_eq!(escape_tsv("plain.txt"), "plain.txt");
+        assert_eq!(escape_tsv("a\tb"), "a\\tb");
+        assert_eq!(escape_tsv("back\\slash"), "back\\\\slash");
+    }
+
+    #[test]
+    fn test_locale_supports_utf8() {
+        assert!(locale_supports_utf8(None, None, None));
+        assert!(locale_supports_utf8(None, None, Some("en_US.UTF-8")));
+        assert!(locale_supports_utf8(Some(""), None, Some("de_DE.utf8")));
+        assert!(!locale_supports_utf8(None, None, Some("C")));
+        assert!(!locale_supports_utf8(
+            Some("POSIX"),
+            None,
+            Some("en_US.UTF-8")
+        ));
+        assert!(!locale_supports_utf8(None, Some("en_US.ISO-8859-1"), None));
+        // LC_ALL takes precedence over LC_CTYPE and LANG
+        assert!(locale_supports_utf8(Some("C.UTF-8"), Some("C"), Some("C")));
+    }
+
+    #[test]
+    fn test_normalize_path_current_dir() {
+        assert_eq!(normalize_path("./a/./b").as_deref(), Some("a/b"));
+        assert_eq!(normalize_path("a/b/").as_deref(), Some("a/b"));
+    }
+
+    #[test]
+    fn test_normalize_path_parent_dir() {
+        assert_eq!(normalize_path("a/b/../c").as_deref(), Some("a/c"));
+        assert_eq!(normalize_path("a/b/c/../../d").as_deref(), Some("a/d"));
+        assert_eq!(
+            normalize_path("/home/u/../v/x").as_deref(),
+            Some("/home/v/x")
+        );
+    }
+
+    #[test]
+    fn test_normalize_path_over_popping() {
+        assert_eq!(normalize_path("../a"), None);
+        assert_eq!(normalize_path("a/../../b"), None);
+        // Absolute paths can't escape `/`
+        assert_eq!(normalize_path("/../a").as_deref(), Some("/a"));
+    }
+
+    #[test]
+    fn test_sort_tree_with_directory_override() {
+        let config: config::Config = toml::from_str(
+            r#"
+[[sort-rules]]
+path = "src"
+mode = "type"
+"#,
+        )
+        .unwrap();
+        let overrides = compile_sort_rules(&config.sort_rules);
+
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "b.txt"]);
+        root.add_path(vec!["src", "a.rs"]);
+        root.add_path(vec!["src", "util", "mod.rs"]);
+        root.add_path(vec!["docs", "b.txt"]);
+        root.add_path(vec!["docs", "a.rs"]);
+        root.add_path(vec!["docs", "util", "mod.rs"]);
+
+        sort_tree(&mut root, SortOrder::Name, &overrides);
+
+        // `src` is type-sorted: directories first, then by extension
+        let src: Vec<_> = root.children["src"].children.keys().collect();
+        assert_eq!(src, vec!["util", "a.rs", "b.txt"]);
+
+        // Siblings use the global name order
+        let docs: Vec<_> = root.children["docs"].children.keys().collect();
+        assert_eq!(docs, vec!["a.rs", "b.txt", "util"]);
+        let top: Vec<_> = root.children.keys().collect();
+        assert_eq!(top, vec!["docs", "src"]);
+    }
+
+    #[test]
+    fn test_compile_sort_rules_skips_invalid() {
+        let rules = vec![
+            config::SortRule {
+                path: "src".to_string(),
+                mode: "sideways".to_string(),
+            },
+            config::SortRule {
+                path: "[".to_string(),
+                mode: "name".to_string(),
+            },
+            config::SortRule {
+                path: "docs".to_string(),
+                mode: "type-name".to_string(),
+            },
+        ];
+
+        let overrides = compile_sort_rules(&rules);
+        assert_eq!(overrides.len(), 1);
+        assert!(matches!(overrides[0].order, SortOrder::TypeName));
+    }
+
+    #[test]
+    fn test_split_mtime() {
+        assert_eq!(
+            split_mtime("1700000000\tsrc/main.rs"),
+            (Some(1_700_000_000), "src/main.rs")
+        );
+        assert_eq!(
+            split_mtime("1700000000.25\ta.txt"),
+            (Some(1_700_000_000), "a.txt")
+        );
+        assert_eq!(split_mtime("no tab.txt"), (None, "no tab.txt"));
+        assert_eq!(split_mtime("name\twith tab"), (None, "name\twith tab"));
+    }
+
+    #[test]
+    fn test_bounded_lines_skips_overlong_line() {
+        let input = format!("short\n{}\r\nafter\r\nlast", "x".repeat(20));
+        let lines: Vec<_> = BoundedLines::new(input.as_bytes(), 10)
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                InputLine::Path("short".to_string()),
+                InputLine::TooLong(21),
+                InputLine::Path("after".to_string()),
+                InputLine::Path("last".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bounded_lines_small_buffer() {
+        // A tiny buffer forces lines to span several fill_buf calls
+        let reader = io::BufReader::with_capacity(2, &b"abcdef\nghij\n"[..]);
+        let lines: Vec<_> = BoundedLines::new(reader, 5).map(Result::unwrap).collect();
+
+        assert_eq!(
+            lines,
+            vec![InputLine::TooLong(6), InputLine::Path("ghij".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_bounded_lines_cr_past_limit_is_not_a_line_ending() {
+        let lines: Vec<_> = BoundedLines::new(&b"abcd\rXYZ\nabcd\r\n"[..], 4)
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![InputLine::TooLong(8), InputLine::Path("abcd".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_bounded_lines_strips_bom() {
+        let lines: Vec<_> =
+            BoundedLines::new(&b"\xEF\xBB\xBFa.txt\r\n\xEF\xBB\xBFb.txt\n"[..], 100)
+                .map(Result::unwrap)
+                .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                InputLine::Path("a.txt".to_string()),
+                InputLine::Path("b.txt".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bounded_lines_unlimited() {
+        let lines: Vec<_> = BoundedLines::new(&b"a.txt\nb.txt\n"[..], usize::MAX)
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                InputLine::Path("a.txt".to_string()),
+                InputLine::Path("b.txt".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_move_files_last() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["b.txt"]);
+        root.add_path(vec!["zeta", "z.txt"]);
+        root.add_path(vec!["a.txt"]);
+        root.add_path(vec!["zeta", "inner", "x.txt"]);
+        root.add_path(vec!["zeta", "a.txt"]);
+        root.add_path(vec!["alpha", "y.txt"]);
+
+        sort_tree(&mut root, SortOrder::Name, &[]);
+        move_files_last(&mut root);
+
+        let top: Vec<_> = root.children.keys().collect();
+        assert_eq!(top, vec!["alpha", "zeta", "a.txt", "b.txt"]);
+        let zeta: Vec<_> = root.children["zeta"].children.keys().collect();
+        assert_eq!(zeta, vec!["inner", "a.txt", "z.txt"]);
+    }
+
+    #[test]
+    fn test_remove_files() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["README.md"]);
+        root.add_path(vec!["src", "main.rs"]);
+        root.add_path(vec!["src", "tree", "mod.rs"]);
+        root.add_dir(vec!["empty"]);
+
+        remove_files(&mut root);
+
+        let top: Vec<_> = root.children.keys().collect();
+        assert_eq!(top, vec!["src", "empty"]);
+        let src: Vec<_> = root.children["src"].children.keys().collect();
+        assert_eq!(src, vec!["tree"]);
+        assert!(!root.children["src"].children["tree"].is_leaf);
+        assert!(root.children["src"].children["tree"].children.is_empty());
+    }
+
     #[test]
     fn test_count_tree_empty() {
         let root = TreeNode::new();
@@ -541,29 +2553,104 @@ mod tests {
     }
 
     #[test]
-    fn test_print_tree_basic() {
+    fn test_find_duplicate_names() {
         let mut root = TreeNode::new();
-        root.is_leaf = false;
-        root.add_path(vec!["test.txt"]);
+        root.add_path(vec!["git", "config"]);
+        root.add_path(vec!["ssh", "config"]);
+        root.add_path(vec!["ssh", "known_hosts"]);
+
+        let duplicates = find_duplicate_names(&root);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].0, "config");
+        assert_eq!(duplicates[0].1, vec!["git/config", "ssh/config"]);
+    }
 
-        let mut trunk = TreeTrunk::default();
-        let color_scheme = ColorScheme::with_colors(false);
+    #[test]
+    fn test_find_duplicate_names_none() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["a.txt"]);
+        root.add_path(vec!["dir", "b.txt"]);
 
-        // This should not panic
-        print_tree(&root, &mut trunk, TreeDepth::root().deeper(), &color_scheme);
+        assert!(find_duplicate_names(&root).is_empty());
     }
 
     #[test]
-    fn test_print_tree_nested() {
+    fn test_flush_completed_moves_entries_to_stats() {
         let mut root = TreeNode::new();
         root.is_leaf = false;
-        root.add_path(vec!["src", "main.rs"]);
-        root.add_path(vec!["src", "lib.rs"]);
+        root.add_path(vec!["first", "a.txt"]);
+        root.add_path(vec!["first", "b.txt"]);
 
-        let mut trunk = TreeTrunk::default();
+        let mut stats = TreeStats::default();
         let color_scheme = ColorScheme::with_colors(false);
+        let options = RenderOptions::default();
+        let mut stdout = io::BufWriter::new(io::stdout().lock());
+        let mut printer = TreeRenderer::new(&mut stdout, &color_scheme, &options);
+
+        // Same top-level entry: nothing is flushed yet
+        let mut flushed = HashSet::new();
+        flush_completed(&mut root, "first", &mut printer, &mut stats, &mut flushed).unwrap();
+        assert_eq!(root.children.len(), 1);
+
+        // A new top-level entry flushes the pending one
+        flush_completed(&mut root, "second", &mut printer, &mut stats, &mut flushed).unwrap();
+        assert!(root.children.is_empty());
+        assert!(flushed.contains("first"));
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.directories, 1);
+    }
+
+    #[test]
+    fn test_color_override_priority() {
+        let plain = Args::parse_from(["chezmoi-files"]);
+        let terminal = ColorEnv {
+            tty: true,
+            ..ColorEnv::default()
+        };
+        let no_color = ColorEnv {
+            no_color: true,
+            ..terminal
+        };
+        let both = ColorEnv {
+            force: true,
+            ..no_color
+        };
+        assert_eq!(color_override(&plain, terminal), None);
+        assert_eq!(
+            color_override(&plain, no_color),
+            Some((false, config::ConfigSource::Env))
+        );
+        assert_eq!(
+            color_override(&plain, both),
+            Some((true, config::ConfigSource::Env))
+        );
+
+        // Output that isn't a terminal is plain unless colors are forced
+        let piped = ColorEnv::default();
+        assert_eq!(
+            color_override(&plain, piped),
+            Some((false, config::ConfigSource::Env))
+        );
+        assert_eq!(
+            color_override(
+                &plain,
+                ColorEnv {
+                    force: true,
+                    ..piped
+                }
+            ),
+            Some((true, config::ConfigSource::Env))
+        );
 
-        // This should not panic
-        print_tree(&root, &mut trunk, TreeDepth::root().deeper(), &color_scheme);
+        let flag = Args::parse_from(["chezmoi-files", "--no-color"]);
+        assert_eq!(
+            color_override(&flag, both),
+            Some((false, config::ConfigSource::Cli))
+        );
+        let always = Args::parse_from(["chezmoi-files", "--color", "always"]);
+        assert_eq!(
+            color_override(&always, no_color),
+            Some((true, config::ConfigSource::Cli))
+        );
     }
 }
@@ -0,0 +1,779 @@
+//! Rendering a tree to text.
+//!
+//! [`TreeRenderer`] draws a [`TreeNode`] with box-drawing connectors to any
+//! [`io::Write`] sink, laid out by [`RenderOptions`]. [`render_to`] writes a
+//! whole tree with the default options, and [`render`] returns it as a `String`.
+//!
+//! # Examples
+//!
+//! ```
+//! use chezmoi_files::{ColorScheme, RenderOptions, TreeNode, TreeRenderer, render, render_to};
+//!
+//! let mut root = TreeNode::new();
+//! root.add_path(vec!["src", "main.rs"]);
+//! root.add_path(vec!["README.md"]);
+//!
+//! let text = render(&root, &ColorScheme::with_colors(false));
+//! assert_eq!(text, ".\n├── src\n│   └── main.rs\n└── README.md\n");
+//!
+//! let mut bytes = Vec::new();
+//! render_to(&mut bytes, &root, &ColorScheme::with_colors(false)).unwrap();
+//! assert_eq!(bytes, text.as_bytes());
+//!
+//! // Layout options go through a `TreeRenderer`
+//! let options = RenderOptions {
+//!     ascii: true,
+//!     ..RenderOptions::default()
+//! };
+//! let scheme = ColorScheme::with_colors(false);
+//! let mut bytes = Vec::new();
+//! TreeRenderer::new(&mut bytes, &scheme, &options)
+//!     .write_tree(".", &root)
+//!     .unwrap();
+//! assert_eq!(bytes, b".\n|-- src\n|   `-- main.rs\n`-- README.md\n");
+//! ```
+
+use crate::color::ColorScheme;
+use crate::tree::{TreeDepth, TreeNode, TreeParams, TreePart, TreeTrunk};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Renders `root` as a tree, starting with a `.` line for the root itself.
+///
+/// Entries are colored by `color_scheme` and listed in the tree's own order.
+/// Every line, the last included, ends in a newline.
+#[must_use]
+pub fn render(root: &TreeNode, color_scheme: &ColorScheme) -> String {
+    let mut text = Vec::new();
+    // Writing to a Vec can't fail
+    let _ = render_to(&mut text, root, color_scheme);
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+/// Writes the tree [`render`] would return to `writer`, one line at a time.
+///
+/// Each line is a separate write, so wrap unbuffered sinks such as a file or
+/// stdout in an [`io::BufWriter`].
+///
+/// # Errors
+///
+/// Returns the first error from `writer`.
+pub fn render_to<W: Write + ?Sized>(
+    writer: &mut W,
+    root: &TreeNode,
+    color_scheme: &ColorScheme,
+) -> io::Result<()> {
+    let options = RenderOptions::default();
+    let mut renderer = TreeRenderer::new(writer, color_scheme, &options);
+    renderer.write_tree(".", root)?;
+    renderer.flush()
+}
+
+/// Options controlling how the tree and each of its lines are laid out.
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)] // independent layout toggles, one per flag
+pub struct RenderOptions {
+    /// Text placed between the connector prefix and the entry name.
+    pub separator: String,
+    /// Whether chains of single-child directories render as one `a/b/c` label.
+    pub collapse: bool,
+    /// Labels longer than this many characters have their middle elided.
+    pub max_label_width: Option<usize>,
+    /// Directories at this depth are rendered without their contents.
+    pub max_depth: Option<usize>,
+    /// Whether connectors are drawn with plain ASCII instead of box drawing characters.
+    pub ascii: bool,
+    /// Whether rows consist of the connector prefix alone, for debugging the layout.
+    pub prefix_only: bool,
+    /// Whether a blank line follows each top-level entry except the last.
+    pub group_separator: bool,
+    /// Whether directories without any files below them are dimmed.
+    pub dim_empty_dirs: bool,
+    /// Whether the root line is left out.
+    pub null_root: bool,
+    /// Whether each top-level directory's subtree shares one color.
+    pub color_by_top_dir: bool,
+    /// Whether a color carries over to following entries of the same color.
+    pub compact_colors: bool,
+    /// Whether file names are shown without their extension.
+    pub strip_extensions: bool,
+    /// Text written at the start of every line.
+    pub line_prefix: String,
+    /// Whether the last line ends in a newline.
+    pub trailing_newline: bool,
+    /// Lines past this many are dropped, and a notice says how many were kept.
+    pub max_lines: Option<usize>,
+    /// Lines are cut to this many columns, line prefix included.
+    pub width: Option<usize>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            separator: " ".to_string(),
+            collapse: false,
+            max_label_width: None,
+            max_depth: None,
+            ascii: false,
+            prefix_only: false,
+            group_separator: false,
+            dim_empty_dirs: false,
+            null_root: false,
+            color_by_top_dir: false,
+            compact_colors: false,
+            strip_extensions: false,
+            line_prefix: String::new(),
+            trailing_newline: true,
+            max_lines: None,
+            width: None,
+        }
+    }
+}
+
+/// Number of each [`TreePart`] emitted while rendering.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PartCounts {
+    /// Number of `├──` parts.
+    pub edge: usize,
+    /// Number of `│` parts.
+    pub line: usize,
+    /// Number of `└──` parts.
+    pub corner: usize,
+    /// Number of blank parts.
+    pub blank: usize,
+}
+
+impl PartCounts {
+    /// Records the parts making up one rendered row.
+    fn record(&mut self, parts: &[TreePart]) {
+        for part in parts {
+            match part {
+                TreePart::Edge => self.edge += 1,
+                TreePart::Line => self.line += 1,
+                TreePart::Corner => self.corner += 1,
+                TreePart::Blank => self.blank += 1,
+            }
+        }
+    }
+}
+
+/// Writes a tree, and any lines that go with it, to an [`io::Write`] sink.
+///
+/// Holds the state shared across a depth-first traversal: the `TreeTrunk`
+/// tracking which vertical lines are still open, the colors carried between
+/// entries, and counters of the tree parts and lines written so far. Every
+/// line goes through [`line`](Self::line), so the line prefix, width and line
+/// limit of the [`RenderOptions`] apply to all of them.
+///
+/// With `trailing_newline` off, each newline is deferred until the next line
+/// arrives, which lets the final line end without one.
+pub struct TreeRenderer<'a, W: Write + ?Sized> {
+    writer: &'a mut W,
+    color_scheme: &'a ColorScheme,
+    options: &'a RenderOptions,
+    trunk: TreeTrunk,
+    part_counts: PartCounts,
+    /// Number of top-level directories seen so far, for `color_by_top_dir`.
+    top_dirs: usize,
+    /// Color of the top-level directory currently being written.
+    top_color: Option<String>,
+    /// Color left on by the last entry with `compact_colors`.
+    open_color: Option<String>,
+    pending_newline: bool,
+    written: usize,
+    dropped: bool,
+}
+
+impl<'a, W: Write + ?Sized> TreeRenderer<'a, W> {
+    /// Creates a renderer that colors entries with `color_scheme`, lays them
+    /// out by `options` and writes them to `writer`.
+    pub fn new(
+        writer: &'a mut W,
+        color_scheme: &'a ColorScheme,
+        options: &'a RenderOptions,
+    ) -> Self {
+        Self {
+            writer,
+            color_scheme,
+            options,
+            trunk: TreeTrunk::default(),
+            part_counts: PartCounts::default(),
+            top_dirs: 0,
+            top_color: None,
+            open_color: None,
+            pending_newline: false,
+            written: 0,
+            dropped: false,
+        }
+    }
+
+    /// Returns the color scheme entries are colored with.
+    #[must_use]
+    pub const fn color_scheme(&self) -> &'a ColorScheme {
+        self.color_scheme
+    }
+
+    /// Returns the number of each tree part written so far.
+    #[must_use]
+    pub const fn part_counts(&self) -> PartCounts {
+        self.part_counts
+    }
+
+    /// Writes the whole tree: the root line labeled `label`, every entry
+    /// below `root`, and the truncation notice if lines were dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from the writer.
+    pub fn write_tree(&mut self, label: &str, root: &TreeNode) -> io::Result<()> {
+        self.write_root(label)?;
+        self.write_children(root, TreeDepth::root().deeper())?;
+        self.finish_colors()?;
+        let root_lines = usize::from(!self.options.null_root);
+        self.truncation_notice(root_lines + root.node_count())
+    }
+
+    /// Writes the root label of the tree. The root has no prefix, so nothing is
+    /// written in prefix-only mode, and `null_root` leaves it out entirely.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from the writer.
+    pub fn write_root(&mut self, label: &str) -> io::Result<()> {
+        if self.options.prefix_only || self.options.null_root {
+            return Ok(());
+        }
+        let line = self.color_scheme.format_root(label);
+        self.line(&line)
+    }
+
+    /// Writes the children of `node`, found at `depth`, and their subtrees.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from the writer.
+    pub fn write_children(&mut self, node: &TreeNode, depth: TreeDepth) -> io::Result<()> {
+        let last_key = node.children.keys().last();
+
+        for (name, subtree) in &node.children {
+            let is_last = Some(name) == last_key;
+            self.write_entry(name, subtree, is_last, depth)?;
+            if !is_last {
+                self.write_group_separator(depth)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a blank line after a top-level entry when `group_separator` is set.
+    ///
+    /// The line carries no connectors, so the trunk state is unaffected.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from the writer.
+    pub fn write_group_separator(&mut self, depth: TreeDepth) -> io::Result<()> {
+        if self.options.group_separator && depth.0 == 1 {
+            self.line("")?;
+        }
+        Ok(())
+    }
+
+    /// Writes a single entry and, for directories, its whole subtree.
+    ///
+    /// With `collapse` enabled, a directory whose only child is another
+    /// directory is merged with it into a single `a/b` label.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from the writer.
+    pub fn write_entry(
+        &mut self,
+        name: &str,
+        subtree: &TreeNode,
+        is_last: bool,
+        depth: TreeDepth,
+    ) -> io::Result<()> {
+        let stripped = self.options.strip_extensions && subtree.is_leaf;
+        let mut label = if stripped {
+            strip_extension(name).to_string()
+        } else {
+            name.to_string()
+        };
+        let mut subtree = subtree;
+        if self.options.collapse {
+            while !subtree.is_leaf && subtree.children.len() == 1 {
+                let Some((child_name, child)) = subtree.children.get_index(0) else {
+                    break;
+                };
+                if child.is_leaf {
+                    break;
+                }
+                label.push('/');
+                label.push_str(child_name);
+                subtree = child;
+            }
+        }
+        if let Some(max_width) = self.options.max_label_width {
+            label = elide_middle(&label, max_width);
+        }
+        let cut_off = !subtree.children.is_empty()
+            && self.options.max_depth.is_some_and(|max| depth.0 >= max);
+        if cut_off {
+            let _ = write!(label, " ({} hidden)", subtree.node_count());
+        }
+
+        if self.options.color_by_top_dir && depth.0 == 1 {
+            self.top_color = None;
+            if !subtree.is_leaf {
+                self.top_color = self.color_scheme.top_dir_color(self.top_dirs);
+                self.top_dirs += 1;
+            }
+        }
+
+        let params = TreeParams::new(depth, is_last);
+        let parts = self.trunk.new_row(params);
+        self.part_counts.record(parts);
+
+        let prefix: String = if self.options.ascii {
+            parts.iter().map(|part| part.plain_ascii()).collect()
+        } else {
+            parts.iter().map(|part| part.ascii_art()).collect()
+        };
+        let lead = format!("{prefix}{}", self.options.separator);
+        if self.options.prefix_only {
+            self.write_row(&lead, &prefix)?;
+        } else if self.options.dim_empty_dirs && !subtree.is_leaf && !subtree.has_leaves() {
+            let line = self
+                .color_scheme
+                .format_dimmed(&prefix, &self.options.separator, &label);
+            self.write_row(&lead, &line)?;
+        } else {
+            let age_color = subtree
+                .mtime
+                .filter(|_| subtree.is_leaf)
+                .and_then(|mtime| {
+                    self.color_scheme
+                        .age_color(mtime)
+                        .or_else(|| self.color_scheme.newer_color(name, mtime))
+                })
+                .or_else(|| self.top_color.clone())
+                // A stripped label lost the extension its color comes from
+                .or_else(|| {
+                    let color = self.color_scheme.file_color(name).filter(|_| stripped)?;
+                    Some(color.to_string())
+                });
+            let line = self.color_scheme.format_at_depth(
+                &prefix,
+                &self.options.separator,
+                &label,
+                !subtree.is_leaf,
+                depth.0,
+                age_color.as_deref(),
+            );
+            self.write_row(&lead, &line)?;
+        }
+
+        if !subtree.is_leaf && !cut_off {
+            self.write_children(subtree, depth.deeper())?;
+        }
+        Ok(())
+    }
+
+    /// Writes an entry's line, whose prefix and separator are `lead`.
+    ///
+    /// With `compact_colors`, a line whose name is a single colored run keeps
+    /// that color on afterwards instead of resetting it, and a following line in
+    /// the same color leaves out its color code. The pending reset is written
+    /// once the color changes, or by [`finish_colors`](Self::finish_colors).
+    fn write_row(&mut self, lead: &str, line: &str) -> io::Result<()> {
+        let reset = self.color_scheme.reset_code();
+        let run = if self.options.compact_colors {
+            single_color_run(line, lead, reset)
+        } else {
+            None
+        };
+        let continues = run.is_some_and(|(code, _)| self.open_color.as_deref() == Some(code));
+        let close = if self.open_color.is_some() && !continues {
+            reset
+        } else {
+            ""
+        };
+
+        match run {
+            Some((_, name)) if continues => self.line(&format!("{lead}{name}")),
+            Some((code, name)) => {
+                self.open_color = Some(code.to_string());
+                self.line(&format!("{close}{lead}{code}{name}"))
+            }
+            None => {
+                self.open_color = None;
+                self.line(&format!("{close}{line}"))
+            }
+        }
+    }
+
+    /// Writes the reset still pending from `compact_colors`, if any, so the
+    /// color doesn't leak into whatever is written next.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from the writer.
+    pub fn finish_colors(&mut self) -> io::Result<()> {
+        if self.open_color.take().is_some() {
+            // A bare reset, not a line: it adds nothing visible
+            self.writer
+                .write_all(self.color_scheme.reset_code().as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Writes one line of output, unless the line limit has been reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from the writer.
+    pub fn line(&mut self, text: &str) -> io::Result<()> {
+        if self
+            .options
+            .max_lines
+            .is_some_and(|limit| self.written >= limit)
+        {
+            self.dropped = true;
+            return Ok(());
+        }
+        self.written += 1;
+        match self.options.width {
+            Some(width) => {
+                let width = width.saturating_sub(self.options.line_prefix.chars().count());
+                self.write_line(&clip_to_width(text, width))
+            }
+            None => self.write_line(text),
+        }
+    }
+
+    /// Writes a notice if lines were dropped by the line limit, out of `total`
+    /// lines the output would have had.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from the writer.
+    pub fn truncation_notice(&mut self, total: usize) -> io::Result<()> {
+        if !self.dropped {
+            return Ok(());
+        }
+        let notice = format!(
+            "… output truncated (rendered {} of {total} lines)",
+            self.written
+        );
+        self.write_line(&notice)
+    }
+
+    /// Flushes the writer, e.g. to show entries completed so far while
+    /// input is still being read.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from the writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn write_line(&mut self, text: &str) -> io::Result<()> {
+        let prefix = &self.options.line_prefix;
+        if self.options.trailing_newline {
+            writeln!(self.writer, "{prefix}{text}")
+        } else {
+            let newline = if self.pending_newline { "\n" } else { "" };
+            self.pending_newline = true;
+            write!(self.writer, "{newline}{prefix}{text}")
+        }
+    }
+}
+
+/// Splits `line` into its color code and name when it is `lead`, then a
+/// single colored run, then `reset`, as plain entries are formatted.
+fn single_color_run<'l>(line: &'l str, lead: &str, reset: &str) -> Option<(&'l str, &'l str)> {
+    if reset.is_empty() {
+        return None;
+    }
+    let styled = line.strip_prefix(lead)?.strip_suffix(reset)?;
+    let code_end = styled.strip_prefix("\x1b[")?.find('m')? + 3;
+    let (code, name) = styled.split_at(code_end);
+    (!name.is_empty() && !name.contains('\x1b')).then_some((code, name))
+}
+
+/// Returns `name` without its extension for `strip_extensions`. A leading
+/// dot starts a dotfile name rather than an extension, so `.gitignore` is kept.
+fn strip_extension(name: &str) -> &str {
+    name.get(1..)
+        .and_then(|rest| rest.rsplit_once('.'))
+        .filter(|(_, extension)| !extension.is_empty())
+        .map_or(name, |(stem, _)| &name[..=stem.len()])
+}
+
+/// Shortens `label` to at most `max_width` characters by replacing its middle
+/// with `…`, keeping the head and tail. Labels that already fit are returned as-is.
+fn elide_middle(label: &str, max_width: usize) -> String {
+    let len = label.chars().count();
+    if len <= max_width {
+        return label.to_string();
+    }
+
+    let keep = max_width.saturating_sub(1);
+    let head = keep.div_ceil(2);
+    let tail = keep / 2;
+
+    let mut elided: String = label.chars().take(head).collect();
+    elided.push('…');
+    elided.extend(label.chars().skip(len - tail));
+    elided
+}
+
+/// Cuts `line` to at most `width` visible characters, replacing the cut-off
+/// end with `…`. ANSI color codes take no columns and are all kept, so colors
+/// are still reset after a clipped name.
+fn clip_to_width(line: &str, width: usize) -> String {
+    let mut chars = line.chars();
+    let mut visible = 0;
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            visible += 1;
+        }
+    }
+    if visible <= width {
+        return line.to_string();
+    }
+
+    let keep = width.saturating_sub(1);
+    let mut clipped = String::with_capacity(line.len());
+    let mut kept = 0;
+    let mut cut = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            clipped.push(c);
+            for c in chars.by_ref() {
+                clipped.push(c);
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else if kept < keep {
+            clipped.push(c);
+            kept += 1;
+        } else if !cut {
+            clipped.push('…');
+            cut = true;
+        }
+    }
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders `root` labeled `.` with `options` and returns the text.
+    fn render_with(root: &TreeNode, color_scheme: &ColorScheme, options: &RenderOptions) -> String {
+        let mut bytes = Vec::new();
+        TreeRenderer::new(&mut bytes, color_scheme, options)
+            .write_tree(".", root)
+            .unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_render_empty_tree() {
+        let root = TreeNode::new();
+        assert_eq!(render(&root, &ColorScheme::with_colors(false)), ".\n");
+    }
+
+    #[test]
+    fn test_render_nested_tree() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["a", "b", "c.txt"]);
+        root.add_path(vec!["a", "d.txt"]);
+        root.add_path(vec!["e.txt"]);
+
+        assert_eq!(
+            render(&root, &ColorScheme::with_colors(false)),
+            ".\n├── a\n│   ├── b\n│   │   └── c.txt\n│   └── d.txt\n└── e.txt\n"
+        );
+    }
+
+    #[test]
+    fn test_render_to_writer() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "main.rs"]);
+        let scheme = ColorScheme::new();
+
+        let mut bytes = Vec::new();
+        render_to(&mut bytes, &root, &scheme).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), render(&root, &scheme));
+
+        // Works through a trait object too
+        let mut writer: Box<dyn Write> = Box::new(Vec::new());
+        render_to(writer.as_mut(), &root, &scheme).unwrap();
+    }
+
+    #[test]
+    fn test_render_to_reports_write_errors() {
+        struct Failing;
+        impl Write for Failing {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::from(io::ErrorKind::BrokenPipe))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let root = TreeNode::new();
+        let error = render_to(&mut Failing, &root, &ColorScheme::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_render_with_colors() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "main.rs"]);
+
+        assert_eq!(
+            render(&root, &ColorScheme::new()),
+            "\x1b[1;37m.\x1b[0m\n└── \x1b[1;37msrc\x1b[0m\n    └── \x1b[1;31mmain.rs\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_render_options_default_separator() {
+        assert_eq!(RenderOptions::default().separator, " ");
+    }
+
+    #[test]
+    fn test_render_options_layout() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["a", "b", "c.txt"]);
+        root.add_path(vec!["notes.md"]);
+        let scheme = ColorScheme::with_colors(false);
+
+        let options = RenderOptions {
+            collapse: true,
+            strip_extensions: true,
+            null_root: true,
+            ..RenderOptions::default()
+        };
+        assert_eq!(
+            render_with(&root, &scheme, &options),
+            "├── a/b\n│   └── c\n└── notes\n"
+        );
+
+        let options = RenderOptions {
+            max_depth: Some(1),
+            line_prefix: "> ".to_string(),
+            trailing_newline: false,
+            ..RenderOptions::default()
+        };
+        assert_eq!(
+            render_with(&root, &scheme, &options),
+            "> .\n> ├── a (2 hidden)\n> └── notes.md"
+        );
+    }
+
+    #[test]
+    fn test_render_max_lines_and_width() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "main.rs"]);
+        root.add_path(vec!["README.md"]);
+        let scheme = ColorScheme::with_colors(false);
+
+        let options = RenderOptions {
+            max_lines: Some(2),
+            width: Some(8),
+            ..RenderOptions::default()
+        };
+        assert_eq!(
+            render_with(&root, &scheme, &options),
+            ".\n├── src\n… output truncated (rendered 2 of 4 lines)\n"
+        );
+    }
+
+    #[test]
+    fn test_part_counts_record() {
+        let mut counts = PartCounts::default();
+        counts.record(&[TreePart::Line, TreePart::Edge]);
+        counts.record(&[TreePart::Blank, TreePart::Corner]);
+
+        assert_eq!(
+            counts,
+            PartCounts {
+                edge: 1,
+                line: 1,
+                corner: 1,
+                blank: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_write_children_counts_parts() {
+        let mut root = TreeNode::new();
+        root.is_leaf = false;
+        root.add_path(vec!["src", "main.rs"]);
+        root.add_path(vec!["src", "lib.rs"]);
+        root.add_path(vec!["README.md"]);
+
+        let color_scheme = ColorScheme::with_colors(false);
+        let options = RenderOptions::default();
+        let mut bytes = Vec::new();
+        let mut renderer = TreeRenderer::new(&mut bytes, &color_scheme, &options);
+        renderer
+            .write_children(&root, TreeDepth::root().deeper())
+            .unwrap();
+
+        // src (edge), main.rs (line+edge), lib.rs (line+corner), README.md (corner)
+        assert_eq!(
+            renderer.part_counts(),
+            PartCounts {
+                edge: 2,
+                line: 2,
+                corner: 2,
+                blank: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_strip_extension() {
+        assert_eq!(strip_extension("main.rs"), "main");
+        assert_eq!(strip_extension("archive.tar.gz"), "archive.tar");
+        assert_eq!(strip_extension(".gitignore"), ".gitignore");
+        assert_eq!(strip_extension(".config.toml"), ".config");
+        assert_eq!(strip_extension("Makefile"), "Makefile");
+        assert_eq!(strip_extension("trailing."), "trailing.");
+    }
+
+    #[test]
+    fn test_elide_middle() {
+        assert_eq!(elide_middle("short", 10), "short");
+        assert_eq!(elide_middle("exactly10!", 10), "exactly10!");
+        assert_eq!(elide_middle("alpha/beta/gamma/delta", 11), "alpha…delta");
+        assert_eq!(elide_middle("abcdef", 4), "ab…f");
+        assert_eq!(elide_middle("abcdef", 1), "…");
+    }
+
+    #[test]
+    fn test_clip_to_width() {
+        assert_eq!(clip_to_width("├── main.rs", 20), "├── main.rs");
+        assert_eq!(clip_to_width("├── main.rs", 11), "├── main.rs");
+        assert_eq!(clip_to_width("├── main.rs", 8), "├── mai…");
+        assert_eq!(clip_to_width("├── main.rs", 1), "…");
+        // Color codes don't count and survive the cut
+        assert_eq!(
+            clip_to_width("├── \x1b[1;31mmain.rs\x1b[0m", 8),
+            "├── \x1b[1;31mmai…\x1b[0m"
+        );
+    }
+}
@@ -20,6 +20,9 @@
 //! ```
 
 use indexmap::IndexMap;
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
 
 /// A **tree part** is a single character in the tree structure.
 ///
@@ -50,6 +53,24 @@ impl TreePart {
             Self::Blank => "    ",
         }
     }
+
+    /// Turn this tree part into plain ASCII characters, for terminals that
+    /// can't display box drawing characters.
+    #[must_use]
+    pub const fn plain_ascii(self) -> &'static str {
+        match self {
+            Self::Edge => "|--",
+            Self::Line => "|   ",
+            Self::Corner => "`--",
+            Self::Blank => "    ",
+        }
+    }
+}
+
+impl fmt::Display for TreePart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.ascii_art())
+    }
 }
 
 /// A **tree trunk** builds up arrays of tree parts over multiple depths.
@@ -163,6 +184,24 @@ impl TreeDepth {
     }
 }
 
+/// File and directory counts for the nodes below a [`TreeNode`], as returned
+/// by [`TreeNode::count`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TreeCounts {
+    /// Leaf nodes.
+    pub files: usize,
+    /// Non-leaf nodes.
+    pub directories: usize,
+}
+
+impl TreeCounts {
+    /// Returns the number of files and directories together.
+    #[must_use]
+    pub const fn total(&self) -> usize {
+        self.files + self.directories
+    }
+}
+
 /// A structure representing a node in a tree.
 ///
 /// This structure is used to represent a node in a tree. Each node has a collection
@@ -175,11 +214,17 @@ impl TreeDepth {
 ///   This represents the children of the node.
 /// * `is_leaf` - A boolean flag that indicates whether the node is a leaf node
 ///   (i.e., it has no children).
+/// * `sticky` - Whether this directory is kept even when pruning leaves it empty.
+/// * `mtime` - The modification time of a file, when the input provided one.
 pub struct TreeNode {
     /// The children of this node.
     pub children: IndexMap<String, Self>,
     /// Whether this node is a leaf (has no children).
     pub is_leaf: bool,
+    /// Whether this directory survives pruning even without children.
+    pub sticky: bool,
+    /// Modification time in seconds since the Unix epoch, if known.
+    pub mtime: Option<u64>,
 }
 
 impl TreeNode {
@@ -189,13 +234,46 @@ impl TreeNode {
         Self {
             children: IndexMap::new(),
             is_leaf: true,
+            sticky: false,
+            mtime: None,
         }
     }
 
+    /// Returns an iterator over the immediate children as `(name, child)` pairs,
+    /// in insertion order. Equivalent to iterating over `&node`.
+    #[must_use]
+    pub fn iter(&self) -> <&Self as IntoIterator>::IntoIter {
+        self.into_iter()
+    }
+
+    /// Returns the immediate child called `name`, if there is one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chezmoi_files::TreeNode;
+    ///
+    /// let mut root = TreeNode::new();
+    /// root.add_path(vec!["src", "main.rs"]);
+    ///
+    /// assert!(root.child("src").is_some_and(|src| src.child("main.rs").is_some()));
+    /// assert!(root.child("main.rs").is_none());
+    /// ```
+    #[must_use]
+    pub fn child(&self, name: &str) -> Option<&Self> {
+        self.children.get(name)
+    }
+
+    /// Returns the immediate child called `name` mutably, if there is one.
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut Self> {
+        self.children.get_mut(name)
+    }
+
     /// Adds a path to the tree structure.
     ///
     /// The path is split into parts, and each part is added as a node in the tree.
-    /// If a part already exists, it is reused.
+    /// If a part already exists, it is reused. Empty parts, as left by a doubled
+    /// or trailing `/`, are skipped.
     ///
     /// # Type Parameters
     ///
@@ -204,20 +282,386 @@ impl TreeNode {
     /// # Arguments
     ///
     /// * `parts` - An iterable of path components to add to the tree.
-    pub fn add_path<I>(&mut self, parts: I)
+    ///
+    /// # Returns
+    ///
+    /// The node for the last part, so callers can attach metadata such as `mtime`.
+    pub fn add_path<I>(&mut self, parts: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut current = self;
+        for part in parts {
+            let part = part.as_ref();
+            if part.is_empty() {
+                continue;
+            }
+            current.is_leaf = false;
+            current = current.children.entry(part.to_string()).or_default();
+        }
+        current
+    }
+
+    /// Adds a path like [`add_path`](Self::add_path), but inserts each new
+    /// component at its sorted position among its siblings.
+    ///
+    /// A tree built only with this method is already ordered by name, so no
+    /// separate sort pass is needed. Each insert shifts the later siblings, so
+    /// this trades insert cost for skipping that pass.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chezmoi_files::TreeNode;
+    ///
+    /// let mut root = TreeNode::new();
+    /// root.add_path_sorted(vec!["b.txt"]);
+    /// root.add_path_sorted(vec!["a.txt"]);
+    ///
+    /// assert_eq!(root.leaf_paths(), vec!["a.txt", "b.txt"]);
+    /// ```
+    pub fn add_path_sorted<I>(&mut self, parts: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut current = self;
+        for part in parts {
+            let part = part.as_ref();
+            if part.is_empty() {
+                continue;
+            }
+            current.is_leaf = false;
+            let index = match current
+                .children
+                .binary_search_by(|key, _| key.as_str().cmp(part))
+            {
+                Ok(index) => index,
+                Err(index) => {
+                    current
+                        .children
+                        .shift_insert(index, part.to_string(), Self::default());
+                    index
+                }
+            };
+            current = &mut current.children[index];
+        }
+        current
+    }
+
+    /// Adds a directory to the tree structure, creating any missing parents.
+    ///
+    /// Unlike [`add_path`](Self::add_path), the final part is a directory even when it
+    /// has no children, and it is marked sticky so pruning never removes it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chezmoi_files::TreeNode;
+    ///
+    /// let mut root = TreeNode::new();
+    /// root.add_dir(vec!["bin"]);
+    ///
+    /// assert!(!root.children["bin"].is_leaf);
+    /// assert!(root.children["bin"].children.is_empty());
+    /// ```
+    pub fn add_dir<I>(&mut self, parts: I)
     where
         I: IntoIterator,
         I::Item: AsRef<str>,
     {
         let mut current = self;
         for part in parts {
+            let part = part.as_ref();
+            if part.is_empty() {
+                continue;
+            }
             current.is_leaf = false;
-            let part_str = part.as_ref().to_string();
-            current = current.children.entry(part_str).or_default();
+            current = current.children.entry(part.to_string()).or_default();
+        }
+        current.is_leaf = false;
+        current.sticky = true;
+    }
+
+    /// Returns the full path of every leaf below this node, joined with `/`.
+    ///
+    /// Paths are returned in tree order (depth-first, following child order).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chezmoi_files::TreeNode;
+    ///
+    /// let mut root = TreeNode::new();
+    /// root.add_path(vec!["src", "main.rs"]);
+    /// root.add_path(vec!["README.md"]);
+    ///
+    /// assert_eq!(root.leaf_paths(), vec!["src/main.rs", "README.md"]);
+    /// ```
+    #[must_use]
+    pub fn leaf_paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        self.collect_leaf_paths("", &mut paths);
+        paths
+    }
+
+    /// Returns the number of distinct paths below this node, i.e. its leaf count.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chezmoi_files::TreeNode;
+    ///
+    /// let mut root = TreeNode::new();
+    /// root.add_path(vec!["src", "main.rs"]);
+    /// root.add_path(vec!["src", "lib.rs"]);
+    ///
+    /// assert_eq!(root.path_count(), 2);
+    /// ```
+    #[must_use]
+    pub fn path_count(&self) -> usize {
+        self.children
+            .values()
+            .map(|child| if child.is_leaf { 1 } else { child.path_count() })
+            .sum()
+    }
+
+    /// Returns whether any file (leaf) exists below this node, at any depth.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chezmoi_files::TreeNode;
+    ///
+    /// let mut root = TreeNode::new();
+    /// root.add_dir(vec!["empty", "nested"]);
+    /// assert!(!root.has_leaves());
+    ///
+    /// root.add_path(vec!["src", "main.rs"]);
+    /// assert!(root.has_leaves());
+    /// ```
+    #[must_use]
+    pub fn has_leaves(&self) -> bool {
+        self.children
+            .values()
+            .any(|child| child.is_leaf || child.has_leaves())
+    }
+
+    /// Returns the number of nodes below this node, files and directories alike.
+    ///
+    /// The node itself is not counted.
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.children
+            .values()
+            .map(|child| 1 + child.node_count())
+            .sum()
+    }
+
+    /// Counts the files (leaves) and directories (non-leaves) below this node.
+    ///
+    /// The node itself is not counted, so for the root of a tree these are the
+    /// totals shown by `--stats`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chezmoi_files::{TreeCounts, TreeNode};
+    ///
+    /// let mut root = TreeNode::new();
+    /// root.add_path(vec!["src", "a.rs"]);
+    /// root.add_path(vec!["src", "b.rs"]);
+    ///
+    /// assert_eq!(root.count(), TreeCounts { files: 2, directories: 1 });
+    /// ```
+    #[must_use]
+    pub fn count(&self) -> TreeCounts {
+        let files = self.path_count();
+        TreeCounts {
+            files,
+            directories: self.node_count() - files,
+        }
+    }
+
+    /// Counts nodes per depth in a single pass, returning the total and the
+    /// histogram.
+    ///
+    /// Index `d` of the histogram is the number of nodes at depth `d`, with this
+    /// node at depth 0, so index 0 is always 1 and index 1 holds the immediate
+    /// children. The total is the sum of the histogram, this node included.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chezmoi_files::TreeNode;
+    ///
+    /// let mut root = TreeNode::new();
+    /// root.add_path(vec!["src", "main.rs"]);
+    /// root.add_path(vec!["README.md"]);
+    ///
+    /// assert_eq!(root.total_and_by_depth_counts(), (4, vec![1, 2, 1]));
+    /// ```
+    #[must_use]
+    pub fn total_and_by_depth_counts(&self) -> (usize, Vec<usize>) {
+        let mut by_depth = Vec::new();
+        let total = self.count_by_depth(0, &mut by_depth);
+        (total, by_depth)
+    }
+
+    fn count_by_depth(&self, depth: usize, by_depth: &mut Vec<usize>) -> usize {
+        if by_depth.len() <= depth {
+            by_depth.push(0);
+        }
+        by_depth[depth] += 1;
+
+        1 + self
+            .children
+            .values()
+            .map(|child| child.count_by_depth(depth + 1, by_depth))
+            .sum::<usize>()
+    }
+
+    /// Keeps only the leaves whose full path matches `pattern`, plus the
+    /// directories leading to them. Directories left without matches are removed,
+    /// unless they are sticky (see [`add_dir`](Self::add_dir)).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `pattern` is not a valid glob; the tree is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chezmoi_files::TreeNode;
+    ///
+    /// let mut root = TreeNode::new();
+    /// root.add_path(vec!["src", "main.rs"]);
+    /// root.add_path(vec!["README.md"]);
+    ///
+    /// root.prune_to_matching("*.rs").unwrap();
+    /// assert_eq!(root.leaf_paths(), vec!["src/main.rs"]);
+    /// ```
+    pub fn prune_to_matching(&mut self, pattern: &str) -> Result<(), glob::PatternError> {
+        let pattern = glob::Pattern::new(pattern)?;
+        self.retain_matching("", &pattern);
+        Ok(())
+    }
+
+    fn retain_matching(&mut self, prefix: &str, pattern: &glob::Pattern) {
+        self.children.retain(|name, child| {
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{prefix}/{name}")
+            };
+
+            if child.is_leaf {
+                pattern.matches(&path)
+            } else {
+                child.retain_matching(&path, pattern);
+                child.sticky || !child.children.is_empty()
+            }
+        });
+    }
+
+    fn collect_leaf_paths(&self, prefix: &str, paths: &mut Vec<String>) {
+        for (name, child) in &self.children {
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{prefix}/{name}")
+            };
+
+            if child.is_leaf {
+                paths.push(path);
+            } else {
+                child.collect_leaf_paths(&path, paths);
+            }
         }
     }
 }
 
+/// Iterates over the immediate children of a node as `(name, child)` pairs, in
+/// insertion order.
+///
+/// # Example
+///
+/// ```
+/// use chezmoi_files::TreeNode;
+///
+/// let mut root = TreeNode::new();
+/// root.add_path(vec!["src", "main.rs"]);
+/// root.add_path(vec!["README.md"]);
+///
+/// let names: Vec<&str> = root.iter().map(|(name, _)| name).collect();
+/// assert_eq!(names, vec!["src", "README.md"]);
+///
+/// for (name, child) in &root {
+///     assert_eq!(child.is_leaf, name == "README.md");
+/// }
+/// ```
+impl<'a> IntoIterator for &'a TreeNode {
+    type Item = (&'a str, &'a TreeNode);
+    type IntoIter = std::iter::Map<
+        indexmap::map::Iter<'a, String, TreeNode>,
+        fn((&'a String, &'a TreeNode)) -> (&'a str, &'a TreeNode),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.children
+            .iter()
+            .map(|(name, child)| (name.as_str(), child))
+    }
+}
+
+/// Serializes the tree as nested maps: directories become maps keyed by child
+/// name (in child order), and files become a unit value (`null` in JSON/YAML).
+impl Serialize for TreeNode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_leaf {
+            serializer.serialize_unit()
+        } else {
+            serializer.collect_map(&self.children)
+        }
+    }
+}
+
+/// Deserializes the nested maps written by the `Serialize` impl: maps become
+/// directories (including empty ones) and unit values become files.
+impl<'de> Deserialize<'de> for TreeNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TreeNodeVisitor)
+    }
+}
+
+struct TreeNodeVisitor;
+
+impl<'de> Visitor<'de> for TreeNodeVisitor {
+    type Value = TreeNode;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of child names for a directory, or null for a file")
+    }
+
+    fn visit_unit<E>(self) -> Result<TreeNode, E> {
+        Ok(TreeNode::new())
+    }
+
+    fn visit_none<E>(self) -> Result<TreeNode, E> {
+        Ok(TreeNode::new())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<TreeNode, A::Error> {
+        let mut node = TreeNode::new();
+        node.is_leaf = false;
+        while let Some((name, child)) = map.next_entry::<String, TreeNode>()? {
+            node.children.insert(name, child);
+        }
+        Ok(node)
+    }
+}
+
 impl Default for TreeNode {
     fn default() -> Self {
         Self::new()
@@ -236,6 +680,76 @@ mod tests {
         assert_eq!(TreePart::Blank.ascii_art(), "    ");
     }
 
+    #[test]
+    fn test_tree_part_plain_ascii() {
+        for part in [
+            TreePart::Edge,
+            TreePart::Line,
+            TreePart::Corner,
+            TreePart::Blank,
+        ] {
+            assert!(part.plain_ascii().is_ascii());
+            assert_eq!(
+                part.plain_ascii().chars().count(),
+                part.ascii_art().chars().count()
+            );
+        }
+    }
+
+    #[test]
+    fn test_tree_part_display() {
+        assert_eq!(format!("{}", TreePart::Corner), "└──");
+        assert_eq!(TreePart::Edge.to_string(), TreePart::Edge.ascii_art());
+        assert_eq!(TreePart::Line.to_string(), TreePart::Line.ascii_art());
+        assert_eq!(TreePart::Blank.to_string(), TreePart::Blank.ascii_art());
+    }
+
+    #[test]
+    fn test_tree_node_count_empty() {
+        assert_eq!(TreeNode::new().count(), TreeCounts::default());
+    }
+
+    #[test]
+    fn test_tree_node_count_single_file() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["README.md"]);
+        let counts = root.count();
+        assert_eq!(
+            counts,
+            TreeCounts {
+                files: 1,
+                directories: 0
+            }
+        );
+        assert_eq!(counts.total(), 1);
+    }
+
+    #[test]
+    fn test_tree_node_count_nested() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "a.rs"]);
+        root.add_path(vec!["src", "b.rs"]);
+        assert_eq!(
+            root.count(),
+            TreeCounts {
+                files: 2,
+                directories: 1
+            }
+        );
+
+        // Empty directories count as directories, not files
+        root.add_dir(vec!["empty"]);
+        root.add_path(vec!["src", "nested", "c.rs"]);
+        assert_eq!(
+            root.count(),
+            TreeCounts {
+                files: 3,
+                directories: 3
+            }
+        );
+        assert_eq!(root.count().total(), 6);
+    }
+
     #[test]
     fn test_tree_depth_root() {
         let depth = TreeDepth::root();
@@ -311,6 +825,262 @@ mod tests {
         assert!(src.children.contains_key("lib.rs"));
     }
 
+    #[test]
+    fn test_tree_node_child() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "main.rs"]);
+
+        assert!(!root.child("src").unwrap().is_leaf);
+        assert!(root.child("src").unwrap().child("main.rs").unwrap().is_leaf);
+        assert!(root.child("missing").is_none());
+        assert!(root.child("main.rs").is_none());
+
+        root.child_mut("src").unwrap().mtime = Some(42);
+        assert_eq!(root.children["src"].mtime, Some(42));
+        assert!(root.child_mut("missing").is_none());
+    }
+
+    #[test]
+    fn test_tree_node_add_path_skips_empty_parts() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["", "src", "", "main.rs", ""]);
+        root.add_path_sorted(vec!["src", "", "lib.rs"]);
+        root.add_dir(vec!["", "empty", ""]);
+
+        assert_eq!(root.leaf_paths(), vec!["src/lib.rs", "src/main.rs"]);
+        assert!(root.child("").is_none());
+        assert!(root.child("empty").is_some_and(|dir| !dir.is_leaf));
+
+        // Nothing but empty parts leaves the tree untouched
+        let mut root = TreeNode::new();
+        root.add_path(vec!["", ""]);
+        assert!(root.is_leaf);
+        assert!(root.children.is_empty());
+    }
+
+    /// Checks that no node is named `""` and that a node with children is
+    /// never a leaf.
+    fn assert_consistent(node: &TreeNode) {
+        for (name, child) in node {
+            assert!(!name.is_empty());
+            assert!(child.children.is_empty() || !child.is_leaf, "{name:?}");
+            assert_consistent(child);
+        }
+    }
+
+    /// Checks that every node's children are in name order.
+    fn assert_sorted(node: &TreeNode) {
+        assert!(node.children.keys().is_sorted());
+        node.children.values().for_each(assert_sorted);
+    }
+
+    #[test]
+    fn test_tree_node_add_path_fuzz() {
+        const COMPONENTS: [&str; 9] = ["", "a", "b", " ", ".", "..", "a b", "é", "\t"];
+
+        // xorshift64: deterministic, so failures reproduce
+        let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for round in 0..200 {
+            // add_path_sorted relies on every sibling list being sorted, so
+            // it only gets trees of its own
+            let sorted = round % 2 == 0;
+            let mut root = TreeNode::new();
+            for _ in 0..(next() % 20) {
+                let len = next() % 6;
+                let parts: Vec<&str> = (0..len)
+                    .map(|_| {
+                        let index = usize::try_from(next() % 9).unwrap();
+                        COMPONENTS[index]
+                    })
+                    .collect();
+                if sorted {
+                    root.add_path_sorted(&parts);
+                } else if next() % 2 == 0 {
+                    root.add_path(&parts);
+                } else {
+                    root.add_dir(&parts);
+                }
+                assert_consistent(&root);
+            }
+            assert_eq!(root.count().total(), root.node_count());
+            if sorted {
+                assert_sorted(&root);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tree_node_add_path_sorted() {
+        let mut root = TreeNode::new();
+        root.add_path_sorted(vec!["src", "main.rs"]);
+        root.add_path_sorted(vec!["README.md"]);
+        root.add_path_sorted(vec!["src", "lib.rs"]);
+        root.add_path_sorted(vec!["Cargo.toml"]);
+        root.add_path_sorted(vec!["src", "color.rs"]);
+        root.add_path_sorted(vec!["src", "main.rs"]);
+
+        let keys: Vec<&String> = root.children.keys().collect();
+        assert_eq!(keys, vec!["Cargo.toml", "README.md", "src"]);
+        let keys: Vec<&String> = root.children["src"].children.keys().collect();
+        assert_eq!(keys, vec!["color.rs", "lib.rs", "main.rs"]);
+        assert!(root.children["src"].children["main.rs"].is_leaf);
+    }
+
+    #[test]
+    fn test_tree_node_leaf_paths() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "main.rs"]);
+        root.add_path(vec!["src", "nested", "mod.rs"]);
+        root.add_path(vec!["README.md"]);
+
+        assert_eq!(
+            root.leaf_paths(),
+            vec!["src/main.rs", "src/nested/mod.rs", "README.md"]
+        );
+        assert!(TreeNode::new().leaf_paths().is_empty());
+    }
+
+    #[test]
+    fn test_tree_node_path_and_node_count() {
+        let mut root = TreeNode::new();
+        assert_eq!(root.path_count(), 0);
+        assert_eq!(root.node_count(), 0);
+
+        root.add_path(vec!["src", "main.rs"]);
+        root.add_path(vec!["src", "lib.rs"]);
+        root.add_path(vec!["src", "tree", "mod.rs"]);
+        root.add_path(vec!["docs", "guide", "intro.md"]);
+        root.add_path(vec!["README.md"]);
+
+        // Leaves: main.rs, lib.rs, mod.rs, intro.md, README.md
+        assert_eq!(root.path_count(), 5);
+        // Plus directories: src, tree, docs, guide
+        assert_eq!(root.node_count(), 9);
+        assert_eq!(root.path_count(), root.leaf_paths().len());
+    }
+
+    #[test]
+    fn test_tree_node_total_and_by_depth_counts() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "main.rs"]);
+        root.add_path(vec!["src", "tree", "mod.rs"]);
+        root.add_path(vec!["src", "tree", "node.rs"]);
+        root.add_path(vec!["docs", "guide.md"]);
+        root.add_path(vec!["README.md"]);
+
+        let (total, by_depth) = root.total_and_by_depth_counts();
+        // root; src, docs, README.md; main.rs, tree, guide.md; mod.rs, node.rs
+        assert_eq!(by_depth, vec![1, 3, 3, 2]);
+        assert_eq!(total, 9);
+        assert_eq!(total, root.node_count() + 1);
+
+        let leaf = TreeNode::new();
+        assert_eq!(leaf.total_and_by_depth_counts(), (1, vec![1]));
+    }
+
+    #[test]
+    fn test_tree_node_prune_to_matching() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "main.rs"]);
+        root.add_path(vec!["src", "README.md"]);
+        root.add_path(vec!["src", "tree", "mod.rs"]);
+        root.add_path(vec!["docs", "guide.md"]);
+        root.add_path(vec!["build.rs"]);
+
+        root.prune_to_matching("*.rs").unwrap();
+
+        assert_eq!(
+            root.leaf_paths(),
+            vec!["src/main.rs", "src/tree/mod.rs", "build.rs"]
+        );
+        // `docs` has no matches left and is removed entirely
+        assert!(!root.children.contains_key("docs"));
+    }
+
+    #[test]
+    fn test_tree_node_add_dir_is_sticky() {
+        let mut root = TreeNode::new();
+        root.add_dir(vec!["config", "empty"]);
+        root.add_path(vec!["src", "main.rs"]);
+
+        let config = &root.children["config"];
+        assert!(!config.is_leaf);
+        assert!(!config.sticky);
+        let empty = &config.children["empty"];
+        assert!(!empty.is_leaf);
+        assert!(empty.sticky);
+        assert_eq!(root.path_count(), 1);
+
+        root.prune_to_matching("*.md").unwrap();
+        assert!(root.children["config"].children.contains_key("empty"));
+        assert!(!root.children.contains_key("src"));
+    }
+
+    #[test]
+    fn test_tree_node_into_iterator() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["zeta.txt"]);
+        root.add_path(vec!["src", "main.rs"]);
+        root.add_path(vec!["alpha.txt"]);
+
+        let mut visited = Vec::new();
+        for (name, child) in &root {
+            visited.push((name, child.is_leaf));
+        }
+
+        assert_eq!(
+            visited,
+            vec![("zeta.txt", true), ("src", false), ("alpha.txt", true)]
+        );
+    }
+
+    #[test]
+    fn test_tree_node_has_leaves() {
+        let mut root = TreeNode::new();
+        root.add_dir(vec!["scaffold", "empty"]);
+        root.add_path(vec!["src", "main.rs"]);
+
+        assert!(root.has_leaves());
+        assert!(root.children["src"].has_leaves());
+        assert!(!root.children["scaffold"].has_leaves());
+    }
+
+    #[test]
+    fn test_tree_node_json_round_trip() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "main.rs"]);
+        root.add_dir(vec!["bin"]);
+        root.add_path(vec!["README.md"]);
+
+        let json = serde_json::to_string(&root).unwrap();
+        assert_eq!(
+            json,
+            r#"{"src":{"main.rs":null},"bin":{},"README.md":null}"#
+        );
+
+        let parsed: TreeNode = serde_json::from_str(&json).unwrap();
+        assert!(!parsed.children["bin"].is_leaf);
+        assert!(parsed.children["README.md"].is_leaf);
+        assert_eq!(parsed.leaf_paths(), root.leaf_paths());
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn test_tree_node_prune_to_matching_invalid_glob() {
+        let mut root = TreeNode::new();
+        root.add_path(vec!["src", "main.rs"]);
+
+        assert!(root.prune_to_matching("[").is_err());
+        assert_eq!(root.leaf_paths(), vec!["src/main.rs"]);
+    }
+
     #[test]
     fn test_tree_trunk_new_row_first() {
         let mut trunk = TreeTrunk::default();
@@ -0,0 +1,68 @@
+//! Integration tests for `--from-chezmoi-source`.
+//!
+//! A stub `chezmoi` script is placed on `PATH` in place of the real binary.
+
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+fn test_temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("chezmoi-source-test-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs the binary with `PATH` set to `path_dir` only and stdin closed.
+fn run_with_path(path_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
+        .args(args)
+        .env("PATH", path_dir)
+        .stdin(Stdio::null())
+        .output()
+        .expect("Failed to execute command")
+}
+
+#[test]
+fn test_from_chezmoi_source_renders_managed_paths() {
+    let temp_dir = test_temp_dir("stub");
+    let stub = temp_dir.join("chezmoi");
+    fs::write(
+        &stub,
+        "#!/bin/sh\n\
+         [ \"$1 $2 $3 $4 $5\" = \"managed --path-style absolute --source /src/dotfiles\" ] || exit 2\n\
+         echo /home/tester/.zshrc\n\
+         echo /home/tester/.config/fish/config.fish\n",
+    )
+    .unwrap();
+    fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = run_with_path(
+        &temp_dir,
+        &["--no-color", "--from-chezmoi-source", "/src/dotfiles"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(stdout.contains(".zshrc"));
+    assert!(stdout.contains("config.fish"));
+
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_from_chezmoi_source_without_chezmoi_on_path() {
+    let temp_dir = test_temp_dir("missing");
+
+    let output = run_with_path(&temp_dir, &["--from-chezmoi-source", "/src/dotfiles"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("`chezmoi` was not found on PATH"));
+
+    let _ = fs::remove_dir_all(&temp_dir);
+}
@@ -3,7 +3,8 @@
 //! Tests configuration initialization, display, and default config output.
 
 use std::fs;
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 
 fn test_temp_dir() -> std::path::PathBuf {
     let thread_name = std::thread::current()
@@ -31,6 +32,7 @@ fn test_config_init_creates_file() {
     let output = Command::new("cargo")
         .args(["run", "--quiet", "--", "config", "--init"])
         .env("HOME", &temp_dir)
+        .env_remove("CHEZMOI_FILES")
         .env("XDG_CONFIG_HOME", &temp_dir)
         .output()
         .expect("Failed to execute command");
@@ -59,6 +61,8 @@ fn test_config_init_existing_file() {
     let output = Command::new("cargo")
         .args(["run", "--quiet", "--", "config", "--init"])
         .env("HOME", &temp_dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
         .output()
         .expect("Failed to execute command");
 
@@ -101,6 +105,8 @@ enabled = false
     let output = Command::new("cargo")
         .args(["run", "--quiet", "--", "config"])
         .env("HOME", &temp_dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
         .output()
         .expect("Failed to execute command");
 
@@ -117,3 +123,469 @@ enabled = false
     // Cleanup
     let _ = fs::remove_dir_all(&temp_dir);
 }
+
+#[test]
+fn test_dump_config_merges_cli_overrides() {
+    let temp_dir = test_temp_dir();
+    let config_dir = temp_dir.join(".config").join("chezmoi");
+    fs::create_dir_all(&config_dir).unwrap();
+
+    let config_file = config_dir.join("chezmoi-files.toml");
+    fs::write(
+        &config_file,
+        r#"
+[excluded-files]
+files = ["from-config.tmp"]
+"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
+        .args(["--dump-config", "--exclude", "from-cli.log"])
+        .env("HOME", &temp_dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("[excluded-files]"));
+    assert!(stdout.contains("from-config.tmp"));
+    assert!(stdout.contains("from-cli.log"));
+
+    // The dump must itself be a valid configuration file
+    assert!(toml::from_str::<toml::Table>(&stdout).is_ok());
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_no_default_colors_uses_only_config_rules() {
+    let temp_dir = test_temp_dir();
+    let config_dir = temp_dir.join(".config").join("chezmoi");
+    fs::create_dir_all(&config_dir).unwrap();
+
+    fs::write(
+        config_dir.join("chezmoi-files.toml"),
+        r#"
+[colors.extensions]
+".rs" = "green"
+"#,
+    )
+    .unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
+        .arg("--no-default-colors")
+        .env("HOME", &temp_dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
+        .env("CLICOLOR_FORCE", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child process");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"main.rs\nscript.py\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("\x1b[1;32mmain.rs"));
+    // Not the built-in red: .py falls back to the default file color
+    assert!(stdout.contains("\x1b[1;34mscript.py"));
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_data_files_table_in_chezmoi_toml_is_used_as_fallback() {
+    let temp_dir = test_temp_dir().with_extension("embedded");
+    let config_dir = temp_dir.join(".config").join("chezmoi");
+    fs::create_dir_all(&config_dir).unwrap();
+    let _ = fs::remove_file(config_dir.join("chezmoi-files.toml"));
+
+    fs::write(
+        config_dir.join("chezmoi.toml"),
+        r#"
+[data]
+email = "me@example.com"
+
+[data.files.excluded-files]
+files = ["*.bak"]
+"#,
+    )
+    .unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
+        .args(["--no-color", "--verbose"])
+        .env("HOME", &temp_dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child process");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            b"notes.bak
+keep.txt
+",
+        )
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(stdout, ".\n└── keep.txt\n");
+    assert!(stderr.contains("using [data.files] from chezmoi.toml"));
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_config_show_reports_data_files_table() {
+    let temp_dir = test_temp_dir().with_extension("embedded-show");
+    let config_dir = temp_dir.join(".config").join("chezmoi");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("chezmoi.toml"),
+        "[data.files.excluded-files]\nfiles = [\"*.bak\"]\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
+        .arg("config")
+        .env("HOME", &temp_dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains(&format!(
+        "Configuration file: {}",
+        config_dir.join("chezmoi.toml").display()
+    )));
+    assert!(stdout.contains("[excluded-files]"));
+    assert!(stdout.contains("*.bak"));
+    assert!(!stdout.contains("does not exist"));
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_legacy_substring_patterns_warn() {
+    let output = run_with_config(
+        "[excluded-files]\nfiles = [\"DS_Store\", \"*.bak\"]\n",
+        &["--no-color"],
+        b"a/.DS_Store\nkeep.txt\n",
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success());
+    assert!(stderr.contains("exclusion \"DS_Store\""));
+    assert!(stderr.contains("replace it with \"*DS_Store\""));
+    assert!(!stderr.contains("*.bak"));
+}
+
+/// Runs `config --init` (plus `extra` args) with `HOME` set to `home`.
+fn run_init(home: &std::path::Path, extra: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
+        .args(["config", "--init"])
+        .args(extra)
+        .env("HOME", home)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
+        .output()
+        .expect("Failed to execute command")
+}
+
+#[test]
+fn test_config_init_fresh_create() {
+    let temp_dir = test_temp_dir();
+    let _ = fs::remove_dir_all(&temp_dir);
+    let config_file = temp_dir
+        .join(".config")
+        .join("chezmoi")
+        .join("chezmoi-files.toml");
+
+    let output = run_init(&temp_dir, &[]);
+
+    assert!(output.status.success());
+    let content = fs::read_to_string(&config_file).unwrap();
+    assert!(toml::from_str::<toml::Table>(&content).is_ok());
+    assert!(content.contains("[excluded-files]"));
+    // The commented template, not a bare serialization of the defaults
+    assert_eq!(
+        content,
+        chezmoi_files::config::Config::default_config_toml()
+    );
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_config_init_refuses_to_overwrite() {
+    let temp_dir = test_temp_dir();
+    let config_dir = temp_dir.join(".config").join("chezmoi");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config_file = config_dir.join("chezmoi-files.toml");
+    fs::write(&config_file, "# my config").unwrap();
+
+    let output = run_init(&temp_dir, &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("already exists"));
+    assert_eq!(fs::read_to_string(&config_file).unwrap(), "# my config");
+    assert!(!config_dir.join("chezmoi-files.toml.bak").exists());
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_config_init_force_overwrites_with_backup() {
+    let temp_dir = test_temp_dir();
+    let config_dir = temp_dir.join(".config").join("chezmoi");
+    fs::create_dir_all(&config_dir).unwrap();
+    let config_file = config_dir.join("chezmoi-files.toml");
+    fs::write(&config_file, "# my config").unwrap();
+
+    let output = run_init(&temp_dir, &["--force"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(config_dir.join("chezmoi-files.toml.bak")).unwrap(),
+        "# my config"
+    );
+    let content = fs::read_to_string(&config_file).unwrap();
+    assert!(content.contains("[excluded-files]"));
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+/// Runs the binary with `config` as the user's configuration file, piping
+/// `input` to stdin.
+fn run_with_config(config: &str, args: &[&str], input: &[u8]) -> std::process::Output {
+    let temp_dir = test_temp_dir();
+    let config_dir = temp_dir.join(".config").join("chezmoi");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("chezmoi-files.toml"), config).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
+        .args(args)
+        .env("HOME", &temp_dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
+        .env_remove("NO_COLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child process");
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+    output
+}
+
+#[test]
+fn test_name_color_applies_to_extensionless_file() {
+    let output = run_with_config(
+        r#"
+[colors]
+folder = "white"
+
+[colors.names]
+"Makefile" = "yellow"
+"#,
+        &["--color", "always"],
+        b"Makefile\nsrc/main.rs\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("\x1b[1;33mMakefile"));
+    assert!(stdout.contains("\x1b[1;37msrc"));
+}
+
+#[test]
+fn test_glob_color_rule_matches_extensionless_file() {
+    let output = run_with_config(
+        r#"
+[[colors.glob-rules]]
+pattern = "Dockerfile*"
+color = "magenta"
+"#,
+        &["--color", "always"],
+        b"Dockerfile\nDockerfile.dev\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("\x1b[1;35mDockerfile\x1b[0m"));
+    assert!(stdout.contains("\x1b[1;35mDockerfile.dev\x1b[0m"));
+}
+
+#[test]
+fn test_show_sources_reports_user_and_cli_overrides() {
+    let output = run_with_config(
+        r#"
+[colors.extensions]
+".rs" = "green"
+"#,
+        &["--show-sources", "--exclude", "*.bak"],
+        b"src/main.rs\n",
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success());
+    assert!(stderr.contains("colors.extensions=user"));
+    assert!(stderr.contains("colors.names=default"));
+    assert!(stderr.contains("excluded-files=CLI"));
+}
+
+#[test]
+fn test_color_always_overrides_disabled_config() {
+    let config = r"
+[colors]
+enabled = false
+";
+
+    let output = run_with_config(config, &[], b"src/main.rs\n");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains('\x1b'));
+
+    let output = run_with_config(config, &["--color=always"], b"src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\x1b[1;31mmain.rs"));
+
+    let output = run_with_config("", &["--color", "never"], b"src/main.rs\n");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains('\x1b'));
+}
+
+#[test]
+fn test_output_format_from_config() {
+    let config = r#"
+[output]
+format = "flat-depth"
+"#;
+
+    let output = run_with_config(config, &[], b"src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert_eq!(stdout, "1\tsrc\n2\tsrc/main.rs\n");
+
+    // --output on the command line wins over the config
+    let output = run_with_config(config, &["--output", "json-paths"], b"src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), r#"["src/main.rs"]"#);
+}
+
+#[test]
+fn test_config_path_honors_xdg_config_home_and_chezmoi_files() {
+    let temp_dir = test_temp_dir();
+    let xdg_dir = temp_dir.join("xdg");
+    let override_dir = temp_dir.join("override");
+    fs::create_dir_all(xdg_dir.join("chezmoi")).unwrap();
+    fs::create_dir_all(&override_dir).unwrap();
+    fs::write(
+        xdg_dir.join("chezmoi").join("chezmoi-files.toml"),
+        "[excluded-files]\nfiles = [\"from-xdg.txt\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        override_dir.join("config.toml"),
+        "[excluded-files]\nfiles = [\"from-override.txt\"]\n",
+    )
+    .unwrap();
+
+    let run = |chezmoi_files: Option<&std::path::Path>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_chezmoi-files"));
+        command
+            .arg("--dump-config")
+            .env("HOME", &temp_dir)
+            .env("XDG_CONFIG_HOME", &xdg_dir)
+            .env_remove("CHEZMOI_FILES");
+        if let Some(dir) = chezmoi_files {
+            command.env("CHEZMOI_FILES", dir);
+        }
+        let output = command.output().expect("Failed to execute command");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert!(run(None).contains("from-xdg.txt"));
+    let overridden = run(Some(&override_dir));
+    assert!(overridden.contains("from-override.txt"));
+    assert!(!overridden.contains("from-xdg.txt"));
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_config_folder_file_and_extension_colors() {
+    let output = run_with_config(
+        r#"
+[colors]
+folder = "magenta"
+default-file = "yellow"
+
+[colors.extensions]
+".rs" = "cyan"
+"#,
+        &["--color", "always"],
+        b"src/main.rs\nMakefile\nnotes.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("\x1b[1;35msrc\x1b[0m"));
+    assert!(stdout.contains("\x1b[1;36mmain.rs\x1b[0m"));
+    assert!(stdout.contains("\x1b[1;33mMakefile\x1b[0m"));
+    // Fields left out keep the built-in defaults
+    assert!(stdout.contains("\x1b[1;36mnotes.md\x1b[0m"));
+}
+
+#[test]
+fn test_strip_pwd_false_keeps_current_directory() {
+    let cwd = std::env::current_dir().unwrap();
+    let input = format!("{}/src/main.rs\n", cwd.display());
+    let top = cwd.components().nth(1).unwrap();
+    let top = top.as_os_str().to_string_lossy();
+
+    let output = run_with_config("", &[], input.as_bytes());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(".\n└── src\n"));
+
+    let config = "[input]\nstrip-pwd = false\n";
+    let output = run_with_config(config, &["--show-sources"], input.as_bytes());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.starts_with(&format!(".\n└── {top}\n")));
+    assert!(stdout.ends_with("└── main.rs\n"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("input.strip-pwd=user"));
+
+    // The flag turns stripping off without any config
+    let output = run_with_config("", &["--no-strip"], input.as_bytes());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(&format!(".\n└── {top}\n")));
+}
@@ -48,6 +48,8 @@ fn test_malformed_config_falls_back_to_defaults() {
 
     let mut child = Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
         .env("HOME", temp_dir_str)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
@@ -105,6 +107,8 @@ folder = "cyan"
     let mut child = Command::new("cargo")
         .args(["run", "--quiet", "--"])
         .env("HOME", &temp_dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .spawn()
@@ -154,6 +158,8 @@ enabled = true
     let mut child = Command::new("cargo")
         .args(["run", "--quiet", "--"])
         .env("HOME", &temp_dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CHEZMOI_FILES")
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .spawn()
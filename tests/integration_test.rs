@@ -2,8 +2,47 @@
 //!
 //! Tests tree output, colorization, sorting, statistics, and filtering behavior.
 
-use std::io::Write;
-use std::process::{Command, Stdio};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Runs the binary with the given arguments, piping `input` to stdin.
+fn run_with_input(args: &[&str], input: &[u8]) -> Output {
+    // Stdout is a pipe here, so force the colors a terminal would get
+    run_with_env(args, &[("CLICOLOR_FORCE", "1")], input)
+}
+
+/// Like [`run_with_input`], with extra environment variables set for the child.
+fn run_with_env(args: &[&str], vars: &[(&str, &str)], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
+        .args(args)
+        .env_remove("NO_COLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .envs(vars.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child process");
+
+    // A child that rejects its arguments exits without reading stdin
+    if let Err(e) = child
+        .stdin
+        .take()
+        .expect("Failed to open stdin")
+        .write_all(input)
+    {
+        assert_eq!(
+            e.kind(),
+            io::ErrorKind::BrokenPipe,
+            "Failed to write to stdin"
+        );
+    }
+
+    child.wait_with_output().expect("Failed to read output")
+}
 
 #[test]
 fn test_basic_tree_output() {
@@ -292,3 +331,1293 @@ fn test_glob_pattern_exclusion() {
     assert!(stdout.contains("regular.txt"));
     assert!(stdout.contains("Excluded: 2"));
 }
+
+#[test]
+fn test_verbose_reports_config_path() {
+    let output = run_with_input(&["--verbose", "--no-color"], b"src/main.rs\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stderr.contains("Config path:"));
+    assert!(stderr.contains("chezmoi-files.toml"));
+    assert!(stderr.contains("Patterns:"));
+    // Diagnostics must not leak into the tree output
+    assert!(!stdout.contains("Config path:"));
+}
+
+#[test]
+fn test_root_uses_folder_color() {
+    let output = run_with_input(&[], b"src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let root_line = stdout.lines().next().unwrap();
+    assert_eq!(root_line, "\x1b[1;37m.\x1b[0m");
+}
+
+#[test]
+fn test_root_plain_without_color() {
+    let output = run_with_input(&["--no-color"], b"src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout.lines().next().unwrap(), ".");
+}
+
+#[test]
+fn test_duplicate_names_report() {
+    let output = run_with_input(
+        &["--duplicate-names", "--no-color"],
+        b"dot_config/git/config\ndot_ssh/config\nunique.txt\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let report = stdout
+        .split("Duplicate names:")
+        .nth(1)
+        .expect("report should follow the tree");
+    assert!(report.contains("dot_config/git/config"));
+    assert!(report.contains("dot_ssh/config"));
+    assert!(!report.contains("unique.txt"));
+}
+
+#[test]
+fn test_duplicate_names_only() {
+    let output = run_with_input(
+        &["--duplicate-names-only", "--no-color"],
+        b"a/config\nb/config\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.starts_with("Duplicate names:"));
+    assert!(!stdout.contains("└──"));
+}
+
+#[test]
+fn test_stream_renders_before_input_ends() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chezmoi-files"))
+        .args(["--stream", "--no-color"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child process");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    let stdout = child.stdout.take().expect("Failed to open stdout");
+
+    // First batch: moving on to `second` completes `first`
+    stdin
+        .write_all(b"first/a.txt\nsecond/b.txt\n")
+        .expect("Failed to write to stdin");
+    stdin.flush().unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let reader = thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    // The first group must arrive while stdin is still open
+    let mut early = Vec::new();
+    while early.len() < 3 {
+        let line = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("Expected output before input was closed");
+        early.push(line);
+    }
+    assert_eq!(early, vec![".", "├── first", "│   └── a.txt"]);
+
+    // Second batch, then close stdin
+    stdin.write_all(b"second/c.txt\n").unwrap();
+    drop(stdin);
+
+    let rest: Vec<String> = rx.iter().collect();
+    reader.join().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(rest, vec!["└── second", "    ├── b.txt", "    └── c.txt"]);
+}
+
+#[test]
+fn test_stream_stops_when_top_level_name_reappears() {
+    let output = run_with_env(
+        &["--stream", "--sort", "none"],
+        &[],
+        b"a/1\nb/2\na/3\nc/4\nb/5\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(stderr.contains("\"a\" reappeared after it was printed"));
+    // `a` was already streamed, but nothing is printed twice after that
+    assert_eq!(
+        stdout,
+        ".\n├── a\n│   └── 1\n├── b\n│   ├── 2\n│   └── 5\n├── a\n│   └── 3\n└── c\n    └── 4\n"
+    );
+}
+
+#[test]
+fn test_debug_parts_counts() {
+    let output = run_with_input(
+        &["--debug-parts", "--no-color"],
+        b"src/main.rs\nsrc/lib.rs\nREADME.md\n",
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let line = stderr
+        .lines()
+        .find(|line| line.starts_with("Tree parts:"))
+        .expect("part counts should be printed to stderr");
+    for part in ["edge=", "line=", "corner="] {
+        let count: usize = line
+            .split(part)
+            .nth(1)
+            .and_then(|rest| rest.split(',').next())
+            .and_then(|n| n.trim().parse().ok())
+            .unwrap();
+        assert!(count > 0, "{part} should be nonzero in: {line}");
+    }
+}
+
+#[test]
+fn test_custom_separator() {
+    let output = run_with_input(&["--separator", "─╴", "--no-color"], b"src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("└───╴src"));
+    assert!(stdout.contains("    └───╴main.rs"));
+}
+
+#[test]
+fn test_empty_separator() {
+    let output = run_with_input(&["--separator", "", "--no-color"], b"main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("└──main.rs"));
+}
+
+#[test]
+fn test_no_trailing_newline() {
+    let output = run_with_input(&["--no-trailing-newline", "--no-color"], b"src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, ".\n└── src\n    └── main.rs");
+}
+
+#[test]
+fn test_trailing_newline_by_default() {
+    let output = run_with_input(&["--no-color"], b"src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.ends_with("main.rs\n"));
+}
+
+#[test]
+fn test_collapse_merges_single_child_directories() {
+    let output = run_with_input(
+        &["--no-color", "--collapse"],
+        b"alpha/beta/gamma/delta/file.txt\nalpha/beta/gamma/delta/other.txt\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("└── alpha/beta/gamma/delta\n"));
+    assert!(stdout.contains("    ├── file.txt\n"));
+}
+
+#[test]
+fn test_collapsed_label_with_dot_keeps_folder_color() {
+    let output = run_with_input(&["--collapse"], b"a/b/c.d/file.txt\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // The merged label ends in `c.d`, but it is a directory, not a `.d` file
+    assert!(stdout.contains("└── \x1b[1;37ma/b/c.d\x1b[0m\n"));
+    assert!(stdout.contains("└── \x1b[1;36mfile.txt\x1b[0m\n"));
+}
+
+#[test]
+fn test_max_label_width_elides_collapsed_label() {
+    let output = run_with_input(
+        &[
+            "--no-color",
+            "--stats",
+            "--collapse",
+            "--max-label-width",
+            "11",
+        ],
+        b"alpha/beta/gamma/delta/file.txt\nalpha/beta/gamma/delta/other.txt\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("└── alpha…delta\n"));
+    assert!(stdout.contains("├── file.txt\n"));
+    // Display-only: the stats still count every directory
+    assert!(stdout.contains("Files: 2, Directories: 4"));
+}
+
+#[test]
+fn test_ascii_safe_fallback_with_non_utf8_locale() {
+    let output = run_with_env(
+        &["--no-color", "--ascii-safe-fallback"],
+        &[("LC_ALL", "C")],
+        b"src/main.rs\nsrc/lib.rs\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, ".\n`-- src\n    |-- main.rs\n    `-- lib.rs\n");
+}
+
+#[test]
+fn test_ascii_safe_fallback_keeps_box_drawing_on_utf8_locale() {
+    let output = run_with_env(
+        &["--no-color", "--ascii-safe-fallback"],
+        &[("LC_ALL", "en_US.UTF-8")],
+        b"src/main.rs\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("└── src"));
+}
+
+#[test]
+fn test_count_only_prints_path_count() {
+    let output = run_with_input(
+        &["--count-only"],
+        b"src/main.rs\nsrc/lib.rs\nREADME.md\nsrc/main.rs\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, "3\n");
+}
+
+#[test]
+fn test_normalize_resolves_dot_components() {
+    let output = run_with_input(
+        &["--no-color", "--normalize"],
+        b"./src/main.rs\nsrc/bin/../lib.rs\n../outside.txt\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(stdout, ".\n└── src\n    ├── main.rs\n    └── lib.rs\n");
+    assert!(stderr.contains("escapes the root: ../outside.txt"));
+}
+
+#[test]
+fn test_output_yaml_nested_structure() {
+    let output = run_with_input(
+        &["--output", "yaml"],
+        b"src/main.rs\nsrc/lib.rs\nREADME.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value: serde_yaml::Value = serde_yaml::from_str(&stdout).expect("valid YAML");
+
+    let root = value.as_mapping().expect("root is a mapping");
+    assert_eq!(root.len(), 2);
+
+    let src = value["src"].as_mapping().expect("src is a mapping");
+    assert_eq!(src.len(), 2);
+    assert!(value["src"]["main.rs"].is_null());
+    assert!(value["src"]["lib.rs"].is_null());
+    assert!(value["README.md"].is_null());
+}
+
+#[test]
+fn test_alt_folders_alternates_directory_colors() {
+    let output = run_with_input(&["--alt-folders"], b"outer/inner/deepest/file.txt\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Depth 1 and 3 use the alternate color, depth 2 the regular folder color
+    assert!(stdout.contains("\x1b[1;36mouter\x1b[0m"));
+    assert!(stdout.contains("\x1b[1;37minner\x1b[0m"));
+    assert!(stdout.contains("\x1b[1;36mdeepest\x1b[0m"));
+}
+
+#[test]
+fn test_alt_folders_includes_dotted_directories() {
+    let output = run_with_input(
+        &["--alt-folders"],
+        b".config/fish/conf.d/x.fish
+",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("\x1b[1;36m.config\x1b[0m"));
+    assert!(stdout.contains("\x1b[1;37mfish\x1b[0m"));
+    assert!(stdout.contains("\x1b[1;36mconf.d\x1b[0m"));
+}
+
+#[test]
+fn test_max_line_bytes_skips_overlong_line() {
+    let mut input = b"first.txt\n".to_vec();
+    input.extend(std::iter::repeat_n(b'x', 200));
+    input.extend_from_slice(b"\nsecond.txt\n");
+
+    let output = run_with_input(&["--no-color", "--max-line-bytes", "100"], &input);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(stderr.contains("skipping input line of 200 bytes (limit 100)"));
+    assert_eq!(stdout, ".\n├── first.txt\n└── second.txt\n");
+}
+
+#[test]
+fn test_print_prefix_only() {
+    let output = run_with_input(
+        &["--print-prefix-only"],
+        b"src/main.rs\nsrc/lib.rs\nREADME.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, "├──\n│   ├──\n│   └──\n└──\n");
+    assert!(!stdout.contains("main.rs"));
+    assert!(!stdout.contains('\x1b'));
+}
+
+#[test]
+fn test_find_keeps_matching_leaves_and_ancestors() {
+    let output = run_with_input(
+        &["--no-color", "--find", "*.rs"],
+        b"src/main.rs\nsrc/notes.md\ndocs/guide.md\nbuild.rs\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, ".\n├── src\n│   └── main.rs\n└── build.rs\n");
+}
+
+#[test]
+fn test_files_last_within_each_directory() {
+    let output = run_with_input(
+        &["--no-color", "--files-last"],
+        b"notes.txt\nsrc/main.rs\nsrc/bin/tool.rs\nREADME.md\ndocs/guide.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(
+        stdout,
+        ".\n\
+         ├── src\n\
+         │   ├── bin\n\
+         │   │   └── tool.rs\n\
+         │   └── main.rs\n\
+         ├── docs\n\
+         │   └── guide.md\n\
+         ├── notes.txt\n\
+         └── README.md\n"
+    );
+}
+
+#[test]
+fn test_timing_reports_each_phase() {
+    let output = run_with_input(&["--no-color", "--timing"], b"src/main.rs\nREADME.md\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let timing_lines: Vec<_> = stderr
+        .lines()
+        .filter(|line| line.starts_with("Timing: "))
+        .collect();
+
+    assert_eq!(timing_lines.len(), 3);
+    assert!(timing_lines[0].starts_with("Timing: read input "));
+    assert!(timing_lines[1].starts_with("Timing: build tree "));
+    assert!(timing_lines[2].starts_with("Timing: render "));
+}
+
+#[test]
+fn test_seed_dirs_show_empty_directories() {
+    let output = run_with_input(
+        &["--no-color", "--seed-dirs", "bin,config/empty"],
+        b"config/app.toml\nREADME.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(
+        stdout,
+        ".\n\
+         ├── bin\n\
+         ├── config\n\
+         │   ├── empty\n\
+         │   └── app.toml\n\
+         └── README.md\n"
+    );
+}
+
+#[test]
+fn test_leading_dot_slash_unifies_with_plain_path() {
+    let output = run_with_input(&["--no-color"], b"src/main.rs\n./src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, ".\n└── src\n    └── main.rs\n");
+}
+
+#[test]
+fn test_group_separator_between_top_level_entries() {
+    let output = run_with_input(
+        &["--no-color", "--group-separator"],
+        b"src/main.rs\nsrc/tree/mod.rs\ndocs/guide.md\nREADME.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Three top-level entries: src, docs, README.md
+    let blank_lines = stdout.lines().filter(|line| line.is_empty()).count();
+    assert_eq!(blank_lines, 2);
+    assert_eq!(
+        stdout,
+        ".\n\
+         ├── src\n\
+         │   ├── main.rs\n\
+         │   └── tree\n\
+         │       └── mod.rs\n\
+         \n\
+         ├── docs\n\
+         │   └── guide.md\n\
+         \n\
+         └── README.md\n"
+    );
+}
+
+#[test]
+fn test_json_output_round_trips_through_json_input() {
+    let first = run_with_input(
+        &["--output", "json", "--seed-dirs", "bin"],
+        b"src/main.rs\nsrc/tree/mod.rs\nREADME.md\n",
+    );
+    assert!(first.status.success());
+    let json = String::from_utf8_lossy(&first.stdout).into_owned();
+    let value: serde_json::Value = serde_json::from_str(&json).expect("valid JSON");
+    assert!(value["src"]["main.rs"].is_null());
+    assert!(
+        value["bin"]
+            .as_object()
+            .expect("bin is an object")
+            .is_empty()
+    );
+
+    let second = run_with_input(
+        &["--input-format", "json", "--output", "json"],
+        json.as_bytes(),
+    );
+    assert!(second.status.success());
+    assert_eq!(String::from_utf8_lossy(&second.stdout), json);
+}
+
+#[test]
+fn test_json_input_renders_tree() {
+    let output = run_with_input(
+        &["--no-color", "--input-format", "json"],
+        br#"{"src": {"main.rs": null}, "README.md": null}"#,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, ".\n├── src\n│   └── main.rs\n└── README.md\n");
+}
+
+#[test]
+fn test_json_paths_output_is_sorted_array() {
+    let output = run_with_input(
+        &["--output", "json-paths"],
+        b"src/main.rs\nREADME.md\nsrc/lib.rs\n.DS_Store\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let paths: Vec<String> = serde_json::from_str(&stdout).expect("valid JSON array");
+
+    assert_eq!(paths, vec!["README.md", "src/lib.rs", "src/main.rs"]);
+}
+
+#[test]
+fn test_dim_empty_dirs_dims_only_directories_without_files() {
+    let output = run_with_input(
+        &["--dim-empty-dirs", "--seed-dirs", "scaffold"],
+        b"populated/file.txt\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line_for = |name: &str| {
+        stdout
+            .lines()
+            .find(|line| line.contains(name))
+            .unwrap_or_else(|| panic!("no line for {name}"))
+    };
+
+    assert!(line_for("scaffold").contains("\x1b[2mscaffold"));
+    assert!(!line_for("populated").contains("\x1b[2m"));
+}
+
+#[test]
+fn test_line_prefix_on_every_line() {
+    let output = run_with_input(
+        &["--line-prefix", "> ", "--stats"],
+        b"src/main.rs\nREADME.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout.lines().count(), 6);
+    for line in stdout.lines() {
+        assert!(line.starts_with("> "), "unprefixed line: {line:?}");
+    }
+    // Colors start after the prefix
+    assert!(stdout.starts_with("> \x1b["));
+}
+
+#[test]
+fn test_line_prefix_applies_to_json_output() {
+    let output = run_with_input(
+        &["--line-prefix", "# ", "--output", "json-paths"],
+        b"a.txt\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.lines().all(|line| line.starts_with("# ")));
+}
+
+#[test]
+fn test_empty_input_notes_no_input() {
+    let output = run_with_input(&["--no-color"], b"");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("(no input)"));
+
+    let quiet = run_with_input(&["--no-color", "--quiet"], b"");
+    assert!(!String::from_utf8_lossy(&quiet.stderr).contains("(no input)"));
+}
+
+#[test]
+fn test_filtered_input_is_not_reported_as_empty() {
+    let output = run_with_input(&["--no-color"], b".DS_Store\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!stderr.contains("(no input)"));
+}
+
+#[test]
+fn test_tabs_keep_and_strip() {
+    let input = b"\tindented/file.txt\nnotes\tdraft.md\n";
+
+    let kept = run_with_input(&["--no-color"], input);
+    assert_eq!(
+        String::from_utf8_lossy(&kept.stdout),
+        ".\n├── \tindented\n│   └── file.txt\n└── notes\tdraft.md\n"
+    );
+
+    let stripped = run_with_input(&["--no-color", "--tabs", "strip"], input);
+    assert_eq!(
+        String::from_utf8_lossy(&stripped.stdout),
+        ".\n├── indented\n│   └── file.txt\n└── notesdraft.md\n"
+    );
+}
+
+#[test]
+fn test_color_ext_overrides_default_extension_color() {
+    let output = run_with_input(&["--color-ext", ".rs=green"], b"main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("\x1b[1;32mmain.rs"));
+    assert!(!stdout.contains("\x1b[1;31m"));
+}
+
+#[test]
+fn test_color_ext_rejects_invalid_pair() {
+    // No input: clap exits before reading stdin, so a write could hit a closed pipe
+    let output = run_with_input(&["--color-ext", ".rs"], b"");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("EXT=COLOR"));
+}
+
+#[test]
+fn test_reproducible_output_ignores_input_order() {
+    let first = run_with_input(
+        &["--reproducible"],
+        b"src/main.rs\nREADME.md\nsrc/Lib.rs\ndocs/b.md\ndocs/a.md\n",
+    );
+    let second = run_with_input(
+        &["--reproducible"],
+        b"docs/a.md\nsrc/Lib.rs\ndocs/b.md\nREADME.md\nsrc/main.rs\n",
+    );
+
+    assert!(first.status.success());
+    assert_eq!(first.stdout, second.stdout);
+
+    // Case-sensitive byte order puts uppercase names first
+    let stdout = String::from_utf8_lossy(&first.stdout);
+    let readme = stdout.find("README.md").unwrap();
+    let docs = stdout.find("docs").unwrap();
+    assert!(readme < docs);
+}
+
+#[test]
+fn test_stats_by_depth_report() {
+    let output = run_with_input(
+        &["--no-color", "--stats", "--by-depth"],
+        b"src/main.rs\nsrc/tree/mod.rs\nREADME.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        stdout.ends_with(
+            "Files: 3, Directories: 2, Excluded: 0\nDepth 1: 2\nDepth 2: 2\nDepth 3: 1\n"
+        )
+    );
+}
+
+#[test]
+fn test_sort_does_not_change_stats() {
+    let input = b"zeta/b.rs\nalpha.md\nzeta/a.rs\nbeta/c.txt\n";
+    let summary = |args: &[&str]| {
+        let output = run_with_input(args, input);
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        stdout.lines().last().unwrap_or_default().to_string()
+    };
+
+    let unsorted = summary(&["--no-color", "--stats"]);
+    assert_eq!(unsorted, "Files: 4, Directories: 2, Excluded: 0");
+    for order in ["name", "type", "type-name", "files-first"] {
+        assert_eq!(
+            summary(&["--no-color", "--stats", "--sort", order]),
+            unsorted
+        );
+    }
+}
+
+#[test]
+fn test_stats_include_excluded_breakdown() {
+    let output = run_with_input(
+        &[
+            "--no-color",
+            "--stats=include-excluded",
+            "--exclude",
+            "*.bak",
+        ],
+        b"main.rs\nold.bak\nnotes/todo.bak\npath/DS_Store\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.ends_with(
+        "Files: 1, Directories: 0, Excluded: 3\n\
+         Input paths: 4\n\
+         Excluded by extension:\n  \
+         (none): 1\n  \
+         .bak: 2\n"
+    ));
+}
+
+#[test]
+fn test_tsv_output_columns() {
+    let output = run_with_input(
+        &["--output", "tsv", "--tsv-header"],
+        b"src/main.rs\nREADME.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rows: Vec<Vec<&str>> = stdout
+        .lines()
+        .map(|line| line.split('\t').collect())
+        .collect();
+
+    assert_eq!(rows[0], vec!["depth", "type", "name", "path"]);
+    assert_eq!(rows[1], vec!["1", "dir", "src", "src"]);
+    assert_eq!(rows[2], vec!["2", "file", "main.rs", "src/main.rs"]);
+    assert_eq!(rows[3], vec!["1", "file", "README.md", "README.md"]);
+    assert_eq!(rows.len(), 4);
+}
+
+#[test]
+fn test_first_line_root_becomes_root_label() {
+    let output = run_with_input(
+        &["--no-color", "--first-line-root"],
+        b"/srv/project/\n/srv/project/src/main.rs\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, "/srv/project\n└── src\n    └── main.rs\n");
+}
+
+#[test]
+fn test_first_line_root_alone_renders_root() {
+    let output = run_with_input(&["--no-color", "--first-line-root"], b"/srv/project\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "/srv/project\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_max_siblings_warn_names_wide_directory() {
+    let output = run_with_input(
+        &["--no-color", "--max-siblings-warn", "3"],
+        b"node_modules/a/index.js\nnode_modules/b/index.js\nnode_modules/c/index.js\n\
+          node_modules/d/index.js\nsrc/main.rs\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(stderr.contains("Warning: node_modules has 4 direct children (more than 3)"));
+    assert!(!stderr.contains("src"));
+    // A diagnostic, not a filter: everything is still rendered
+    assert!(stdout.contains("└── d\n"));
+}
+
+#[test]
+fn test_json_output_compact_by_default_and_pretty_on_request() {
+    let input = b"src/main.rs\nREADME.md\n";
+    let compact = run_with_input(&["--output", "json"], input);
+    let pretty = run_with_input(&["--output", "json", "--pretty"], input);
+    let compact = String::from_utf8_lossy(&compact.stdout);
+    let pretty = String::from_utf8_lossy(&pretty.stdout);
+
+    assert_eq!(compact.lines().count(), 1);
+    assert!(pretty.lines().count() > 1);
+    assert!(pretty.contains("\n  \"src\""));
+
+    let compact_value: serde_json::Value = serde_json::from_str(&compact).expect("valid JSON");
+    let pretty_value: serde_json::Value = serde_json::from_str(&pretty).expect("valid JSON");
+    assert_eq!(compact_value, pretty_value);
+}
+
+#[test]
+fn test_age_colors_bucket_files_by_mtime() {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let input = format!("{now}\tnew.txt\n0\told.txt\n");
+    let output = run_with_input(
+        &["--input-format", "timestamped", "--age-colors"],
+        input.as_bytes(),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("\x1b[1;38;2;95;215;95mnew.txt"));
+    assert!(stdout.contains("\x1b[1;38;2;108;108;108mold.txt"));
+}
+
+#[test]
+fn test_bom_is_stripped_from_input() {
+    let output = run_with_input(&["--no-color"], "\u{FEFF}src/main.rs\n".as_bytes());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, ".\n└── src\n    └── main.rs\n");
+}
+
+#[test]
+fn test_examples_subcommand() {
+    let output = run_with_input(&["examples"], b"");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("chezmoi managed | chezmoi-files\n"));
+}
+
+#[test]
+fn test_null_root_omits_root_line() {
+    let output = run_with_input(&["--no-color", "--null-root"], b"src/main.rs\nREADME.md\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, "├── src\n│   └── main.rs\n└── README.md\n");
+}
+
+#[test]
+fn test_json_output_has_no_root_key() {
+    let output = run_with_input(&["--output", "json"], b"src/main.rs\nREADME.md\n");
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid JSON");
+    let keys: Vec<&String> = value.as_object().expect("object").keys().collect();
+
+    assert_eq!(keys, ["README.md", "src"]);
+}
+
+#[test]
+fn test_gzip_input_is_decompressed() {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b"src/main.rs\nREADME.md\n").unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let output = run_with_input(&["--no-color"], &compressed);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, ".\n├── src\n│   └── main.rs\n└── README.md\n");
+}
+
+#[test]
+fn test_flat_depth_output() {
+    let output = run_with_input(
+        &["--output", "flat-depth"],
+        b"src/tree/mod.rs\nsrc/main.rs\nREADME.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(
+        stdout,
+        "1\tsrc\n2\tsrc/tree\n3\tsrc/tree/mod.rs\n2\tsrc/main.rs\n1\tREADME.md\n"
+    );
+}
+
+#[test]
+fn test_line_equal_to_pwd_is_the_root() {
+    let pwd = std::env::current_dir().unwrap();
+    let input = format!("{}\n{}/a.txt\n", pwd.display(), pwd.display());
+    let output = run_with_input(&["--no-color"], input.as_bytes());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), ".\n└── a.txt\n");
+
+    let alone = run_with_input(&["--no-color"], format!("{}\n", pwd.display()).as_bytes());
+    assert_eq!(String::from_utf8_lossy(&alone.stdout), ".\n");
+    assert!(!String::from_utf8_lossy(&alone.stderr).contains("(no input)"));
+}
+
+#[test]
+fn test_guides_color_colors_connectors() {
+    let output = run_with_input(&["--guides-color", "magenta"], b"src/main.rs\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("\x1b[1;35m    └──\x1b[0m \x1b[1;31mmain.rs"));
+}
+
+#[test]
+fn test_legend_lists_categories_in_tree() {
+    let input = b"bin/install.sh\nconfig.toml\nREADME.md\n";
+    let output = run_with_input(&["--legend"], input);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let legend = stdout.lines().last().unwrap();
+
+    assert!(legend.contains("scripts"));
+    assert!(legend.contains("configs"));
+    assert!(legend.contains("docs"));
+    assert!(!legend.contains("source"));
+
+    let output = run_with_input(&["--legend", "--no-color"], input);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("scripts"));
+}
+
+#[test]
+fn test_max_total_lines_truncates_with_notice() {
+    let output = run_with_input(
+        &["--no-color", "--max-total-lines", "3"],
+        b"a.txt\nb.txt\nc.txt\nd.txt\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(
+        lines,
+        vec![
+            ".",
+            "├── a.txt",
+            "├── b.txt",
+            "… output truncated (rendered 3 of 5 lines)"
+        ]
+    );
+}
+
+#[test]
+fn test_color_by_top_dir_colors_subtrees_differently() {
+    let output = run_with_input(
+        &["--color-by-top-dir"],
+        b"api/src/main.rs\napi/README.md\nweb/index.js\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let color_of = |name: &str| {
+        let line = stdout.lines().find(|line| line.contains(name)).unwrap();
+        let start = line.rfind("\x1b[1;38;5;").unwrap();
+        line[start..].split('m').next().unwrap().to_string()
+    };
+
+    // Everything under api/ shares its color, regardless of extension
+    assert_eq!(color_of("api"), color_of("main.rs"));
+    assert_eq!(color_of("api"), color_of("README.md"));
+    assert_eq!(color_of("web"), color_of("index.js"));
+    assert_ne!(color_of("api"), color_of("web"));
+}
+
+#[test]
+fn test_newer_than_highlights_newer_files() {
+    let reference = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+    let reference_mtime = std::fs::metadata(reference)
+        .unwrap()
+        .modified()
+        .unwrap()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let input = format!(
+        "{}\tnew.rs\n{}\told.rs\n",
+        reference_mtime + 100,
+        reference_mtime - 100
+    );
+    let output = run_with_input(
+        &["--input-format", "timestamped", "--newer-than", reference],
+        input.as_bytes(),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("\x1b[1;31m\x1b[7mnew.rs"));
+    assert!(stdout.contains("\x1b[1;31mold.rs"));
+}
+
+#[test]
+fn test_newer_than_missing_reference_fails() {
+    let output = run_with_input(&["--newer-than", "/nonexistent/ref"], b"a.txt\n");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--newer-than"));
+}
+
+#[test]
+fn test_max_depth_hides_deeper_entries() {
+    let output = run_with_input(
+        &["--no-color", "--max-depth", "2"],
+        b"a/b/c/d/file.txt\na/top.txt\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, ".\n└── a\n    ├── b (3 hidden)\n    └── top.txt\n");
+}
+
+#[test]
+fn test_single_file_input_uses_corner() {
+    let output = run_with_input(&["--no-color"], b"README.md\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // The sole child of the root is also its last, so it gets a corner
+    assert_eq!(stdout, ".\n└── README.md\n");
+}
+
+#[test]
+fn test_field_selects_path_from_listing() {
+    let output = run_with_input(
+        &["--no-color", "--field", "-1"],
+        b"-rw-r--r-- 1 me me 10 date src/main.rs\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(stdout, ".\n└── src\n    └── main.rs\n");
+}
+
+#[test]
+fn test_compact_colors_skips_repeated_codes() {
+    let input = b"a.rs\nb.rs\nc.rs\nREADME.md\n";
+    let full = run_with_input(&[], input);
+    let compact = run_with_input(&["--compact-colors"], input);
+    let full = String::from_utf8_lossy(&full.stdout);
+    let compact = String::from_utf8_lossy(&compact.stdout);
+
+    assert_eq!(
+        compact,
+        "\x1b[1;37m.\x1b[0m\n├── \x1b[1;31ma.rs\n├── b.rs\n├── c.rs\n\x1b[0m└── \x1b[1;36mREADME.md\n\x1b[0m"
+    );
+    assert!(compact.matches('\x1b').count() < full.matches('\x1b').count());
+
+    // The text is unchanged once the escape codes are removed
+    let strip = |text: &str| {
+        let mut plain = String::new();
+        let mut in_escape = false;
+        for c in text.chars() {
+            match c {
+                '\x1b' => in_escape = true,
+                'm' if in_escape => in_escape = false,
+                _ if !in_escape => plain.push(c),
+                _ => {}
+            }
+        }
+        plain
+    };
+    assert_eq!(strip(&compact), strip(&full));
+}
+
+#[test]
+fn test_no_color_env_disables_colors() {
+    let output = run_with_env(&[], &[("NO_COLOR", "1")], b".config/fish/config.fish\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("config.fish"));
+    assert!(!stdout.contains('\x1b'));
+
+    // Any value counts, even an empty one
+    let output = run_with_env(&[], &[("NO_COLOR", "")], b"file.txt\n");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains('\x1b'));
+}
+
+#[test]
+fn test_clicolor_force_beats_no_color_env() {
+    let output = run_with_env(
+        &[],
+        &[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")],
+        b"file.txt\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[1;36mfile.txt\x1b[0m"));
+
+    // CLICOLOR_FORCE=0 does not force anything
+    let output = run_with_env(
+        &[],
+        &[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "0")],
+        b"file.txt\n",
+    );
+    assert!(!String::from_utf8_lossy(&output.stdout).contains('\x1b'));
+}
+
+#[test]
+fn test_no_color_flag_beats_clicolor_force() {
+    let output = run_with_env(&["--no-color"], &[("CLICOLOR_FORCE", "1")], b"file.txt\n");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains('\x1b'));
+
+    let output = run_with_env(&["--color", "always"], &[("NO_COLOR", "1")], b"file.txt\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains('\x1b'));
+}
+
+#[test]
+fn test_show_sources_reports_color_env() {
+    let output = run_with_env(&["--show-sources"], &[("NO_COLOR", "1")], b"file.txt\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("colors.enabled=environment"));
+}
+
+#[test]
+fn test_theme256_maps_hex_colors() {
+    let output = run_with_env(
+        &[
+            "--color",
+            "always",
+            "--theme256",
+            "--color-ext",
+            ".rs=#ff8800",
+        ],
+        &[],
+        b"src/main.rs\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[1;38;5;208mmain.rs\x1b[0m"));
+    assert!(!stdout.contains("38;2;"));
+}
+
+#[test]
+fn test_piped_stdout_has_no_colors() {
+    let output = run_with_env(&[], &[], b".config/fish/config.fish\nsrc/main.rs\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("main.rs"));
+    assert!(!stdout.contains('\x1b'));
+
+    let output = run_with_env(&[], &[("CLICOLOR_FORCE", "1")], b"src/main.rs\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\x1b[1;31mmain.rs"));
+}
+
+#[test]
+fn test_no_trim_trailing_slash_keeps_directory() {
+    let output = run_with_env(
+        &["--no-trim-trailing-slash", "--stats"],
+        &[],
+        b"src/\nREADME.md\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(".\n├── src\n└── README.md\n"));
+    assert!(stdout.contains("Files: 1, Directories: 1"));
+
+    // By default the slash is dropped and `src` is just another file
+    let output = run_with_env(&["--stats"], &[], b"src/\nREADME.md\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Files: 2, Directories: 0"));
+}
+
+#[test]
+fn test_multi_root_separates_absolute_trees() {
+    let input = b"/etc/hosts\n/home/me/.zshrc\n";
+    let output = run_with_env(&["--multi-root"], &[], input);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        ".\n├── /etc\n│   └── hosts\n└── /home\n    └── me\n        └── .zshrc\n"
+    );
+
+    // Without it the leading slash is dropped and the trees read as relative
+    let output = run_with_env(&[], &[], input);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("├── etc\n"));
+}
+
+#[test]
+fn test_vertical_bars_align_across_depths() {
+    let output = run_with_env(&[], &[], b"a/b/c.txt\na/b/d.txt\na/e.txt\nf.txt\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Every connector starts a four-column cell, so each `│` sits exactly under
+    // the `├` of its parent entry and names start on a cell boundary
+    for line in stdout.lines().skip(1) {
+        let chars: Vec<char> = line.chars().collect();
+        for (column, c) in chars.iter().enumerate() {
+            if matches!(c, '│' | '├' | '└') {
+                assert_eq!(column % 4, 0, "misaligned {c:?} in {line:?}");
+            }
+        }
+        let name_start = chars.iter().position(|c| c.is_alphanumeric()).unwrap();
+        assert_eq!(name_start % 4, 0, "misaligned name in {line:?}");
+    }
+    assert!(stdout.contains("│   │   └── d.txt\n"));
+}
+
+#[test]
+fn test_render_width_clips_lines_deterministically() {
+    let input = b"docs/a-rather-long-file-name-for-the-docs.md\nshort.txt\n";
+    let args = ["--render-width", "20"];
+    let first = run_with_input(&args, input);
+    assert!(first.status.success());
+    let stdout = String::from_utf8_lossy(&first.stdout);
+    assert!(stdout.contains("│   └── \x1b[1;36ma-rather-lo…\x1b[0m\n"));
+    assert!(stdout.contains("└── \x1b[1;36mshort.txt\x1b[0m\n"));
+
+    // The same width gives the same bytes, with or without colors and a terminal
+    assert_eq!(run_with_input(&args, input).stdout, first.stdout);
+    let plain = run_with_env(&args, &[], input);
+    assert!(String::from_utf8_lossy(&plain.stdout).contains("│   └── a-rather-lo…\n"));
+}
+
+#[test]
+fn test_default_output_matches_library_render() {
+    let paths = ["src/main.rs", "src/color/mod.rs", "README.md", ".zshrc"];
+    let mut root = chezmoi_files::TreeNode::new();
+    for path in paths {
+        root.add_path(path.split('/'));
+    }
+    let expected = chezmoi_files::render(&root, &chezmoi_files::ColorScheme::new());
+
+    // A missing config directory gives the built-in defaults, as the library uses
+    let missing = "/nonexistent/chezmoi-files-test";
+    let output = run_with_env(
+        &[],
+        &[
+            ("CLICOLOR_FORCE", "1"),
+            ("HOME", missing),
+            ("XDG_CONFIG_HOME", missing),
+            ("CHEZMOI_FILES", missing),
+        ],
+        format!("{}\n", paths.join("\n")).as_bytes(),
+    );
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn test_ndjson_output() {
+    let output = run_with_input(
+        &["--output", "ndjson"],
+        b"src/tree/mod.rs\nsrc/main.rs\nREADME \"notes\".md\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let entries: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line is a JSON object"))
+        .collect();
+    assert_eq!(entries.len(), 5);
+    assert_eq!(
+        entries[0],
+        serde_json::json!({"depth": 1, "name": "src", "type": "dir", "path": "src"})
+    );
+    assert_eq!(
+        entries[2],
+        serde_json::json!({
+            "depth": 3,
+            "name": "mod.rs",
+            "type": "file",
+            "path": "src/tree/mod.rs"
+        })
+    );
+    assert_eq!(entries[4]["name"], "README \"notes\".md");
+    for entry in &entries {
+        let object = entry.as_object().unwrap();
+        assert_eq!(object.len(), 4);
+        assert!(
+            ["depth", "name", "type", "path"]
+                .iter()
+                .all(|key| object.contains_key(*key))
+        );
+    }
+}
+
+#[test]
+fn test_large_input_renders_completely() {
+    use std::fmt::Write as _;
+
+    let mut input = String::new();
+    for dir in 0..500 {
+        for file in 0..100 {
+            let _ = writeln!(input, "dir{dir:03}/file{file:03}.txt");
+        }
+    }
+    let output = run_with_env(&["--stats"], &[], input.as_bytes());
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // The root, 500 directories and 50k files, then the blank line and stats
+    assert_eq!(stdout.lines().count(), 1 + 500 + 50_000 + 2);
+    assert!(stdout.contains("    └── file099.txt\n\nFiles"));
+    assert!(stdout.ends_with("Files: 50000, Directories: 500, Excluded: 0\n"));
+}
+
+#[test]
+fn test_dirs_only_prints_only_directories() {
+    let output = run_with_env(&["--dirs-only"], &[], b"src/main.rs\nsrc/lib.rs\n");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), ".\n└── src\n");
+
+    // Hidden files count as neither files nor excluded
+    let output = run_with_env(
+        &["--dirs-only", "--stats"],
+        &[],
+        b"src/main.rs\nsrc/lib.rs\ndocs/api/index.md\nREADME.md\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(".\n├── src\n└── docs\n    └── api\n"));
+    assert!(stdout.contains("Files: 0, Directories: 3, Excluded: 0"));
+}
+
+#[test]
+fn test_strip_extensions_keeps_extension_color() {
+    let output = run_with_input(
+        &["--strip-extensions"],
+        b"src/main.rs\n.gitignore\nMakefile\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("└── \x1b[1;31mmain\x1b[0m\n"));
+    assert!(!stdout.contains("main.rs"));
+    assert!(stdout.contains(".gitignore\x1b[0m\n"));
+    assert!(stdout.contains("\x1b[1;34mMakefile\x1b[0m"));
+    // Directories keep their names
+    assert!(stdout.contains("\x1b[1;37msrc\x1b[0m"));
+}
+
+#[test]
+fn test_files_only_prints_full_paths() {
+    let output = run_with_env(&["--files-only"], &[], b"src/a.rs\ndocs/b.md\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "src/a.rs\ndocs/b.md\n");
+    assert!(!stdout.contains(['├', '└', '│']));
+
+    // Filters and sorting apply, and paths are colored by their file name
+    let output = run_with_input(
+        &["--flat", "--sort", "name", "--exclude", "*.tmp"],
+        b"src/a.rs\ndocs/b.md\nscratch.tmp\n",
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\x1b[1;36mdocs/b.md\x1b[0m\n\x1b[1;31msrc/a.rs\x1b[0m\n"
+    );
+}
+
+#[test]
+fn test_theme_preset_colors_categories() {
+    let output = run_with_input(
+        &["--theme", "deuteranopia", "--sort", "none"],
+        b"run.sh\nmain.rs\nnotes.bin\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[1;38;5;27mrun.sh\x1b[0m"));
+    assert!(stdout.contains("\x1b[1;4;38;5;220mmain.rs\x1b[0m"));
+    assert!(stdout.contains("\x1b[38;5;250mnotes.bin\x1b[0m"));
+
+    let output = run_with_input(&["--theme", "tritanopia"], b"a.rs\n");
+    assert!(!output.status.success());
+}